@@ -20,13 +20,25 @@ use logos::Logos;
 #[derive(Debug)]
 pub struct Tokenizer<'source> {
     lexer: Lexer<'source, TokenKind>,
+    shebang: bool,
     eoi: bool,
 }
 
 impl<'source> Tokenizer<'source> {
     pub fn new(source: &'source str) -> Self {
+        let mut lexer = TokenKind::lexer(source);
+
+        // A leading shebang line (e.g., `#!/usr/bin/env scopeql run -f`) is emitted as a comment
+        // so that scripts can be executed directly.
+        let shebang = source.starts_with("#!");
+        if shebang {
+            let len = source.find(['\r', '\n', '\x0C']).unwrap_or(source.len());
+            lexer.bump(len);
+        }
+
         Self {
-            lexer: TokenKind::lexer(source),
+            lexer,
+            shebang,
             eoi: false,
         }
     }
@@ -44,6 +56,12 @@ impl<'source> Iterator for Tokenizer<'source> {
     type Item = Result<TokenKind, ()>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.shebang {
+            // the lexer has been bumped over the shebang line
+            self.shebang = false;
+            return Some(Ok(TokenKind::Comment));
+        }
+
         match self.lexer.next() {
             Some(Err(())) => Some(Err(())),
             Some(Ok(kind)) => Some(Ok(kind)),
@@ -71,6 +71,13 @@ fn test_comments() {
     "#));
 }
 
+#[test]
+fn test_shebang() {
+    assert_snapshot!(lex("#!/usr/bin/env scopeql run -f\nselect 1;"));
+    assert_snapshot!(lex("#!/usr/bin/env scopeql run -f"));
+    assert_snapshot!(lex("select 1; #!not a shebang"));
+}
+
 #[test]
 fn test_literals_and_escapes() {
     assert_snapshot!(lex(r#"values (1, 'a''b', "c\"d", `e\`f`), (x'FF', 0xFF)"#));
@@ -12,9 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::Write;
+
 use fastrace_reqwest::traceparent_headers;
 use reqwest::IntoUrl;
+use reqwest::RequestBuilder;
 use reqwest::Url;
+use reqwest::header::CONTENT_ENCODING;
+use reqwest::header::CONTENT_TYPE;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::Error;
@@ -26,31 +34,71 @@ use crate::client::protocol::StatementRequest;
 use crate::client::protocol::StatementRequestParams;
 use crate::client::protocol::StatementStatus;
 
+/// The content encoding of request bodies sent to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn content_encoding(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::with_capacity(data.len() / 4),
+                    flate2::Compression::default(),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Client {
     endpoint: Url,
     client: reqwest::Client,
+    compression: Option<Compression>,
 }
 
 impl Client {
     pub fn new<E: IntoUrl>(endpoint: E, client: reqwest::Client) -> Result<Self, Error> {
         match endpoint.into_url() {
-            Ok(endpoint) => Ok(Self { endpoint, client }),
+            Ok(endpoint) => Ok(Self {
+                endpoint,
+                client,
+                compression: None,
+            }),
             Err(err) => Err(Error::new("failed to parse endpoint".to_string()).set_source(err)),
         }
     }
 
+    /// Compresses the request bodies with the given encoding.
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
     #[fastrace::trace]
     pub async fn submit_statement(
         &self,
         request: StatementRequest,
     ) -> Result<Response<StatementStatus>, Error> {
         let url = self.make_url("v1/statements")?;
+        let builder = self.client.post(url).headers(traceparent_headers());
         let response = self
-            .client
-            .post(url)
-            .headers(traceparent_headers())
-            .json(&request)
+            .json_body(builder, &request)?
             .send()
             .await
             .map_err(|err| {
@@ -100,14 +148,12 @@ impl Client {
     }
 
     #[fastrace::trace]
-    pub async fn ingest(&self, request: IngestRequest) -> Result<Response<IngestResult>, Error> {
+    pub async fn ingest(&self, request: &IngestRequest) -> Result<Response<IngestResult>, Error> {
         let format = request.data.format();
         let url = self.make_url("v1/ingest")?;
+        let builder = self.client.post(url).headers(traceparent_headers());
         let response = self
-            .client
-            .post(url)
-            .headers(traceparent_headers())
-            .json(&request)
+            .json_body(builder, request)?
             .send()
             .await
             .map_err(|err| {
@@ -116,6 +162,27 @@ impl Client {
         Response::from_http_response(response).await
     }
 
+    /// Sets the JSON request body, compressed if the client is configured to.
+    fn json_body<T: Serialize>(
+        &self,
+        builder: RequestBuilder,
+        body: &T,
+    ) -> Result<RequestBuilder, Error> {
+        let Some(compression) = self.compression else {
+            return Ok(builder.json(body));
+        };
+
+        let data = serde_json::to_vec(body)
+            .map_err(|err| Error::new("failed to serialize request".to_string()).set_source(err))?;
+        let data = compression
+            .compress(&data)
+            .map_err(|err| Error::new("failed to compress request".to_string()).set_source(err))?;
+        Ok(builder
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_ENCODING, compression.content_encoding())
+            .body(data))
+    }
+
     #[track_caller]
     fn make_url(&self, path: &str) -> Result<Url, Error> {
         self.endpoint
@@ -123,3 +190,42 @@ impl Client {
             .map_err(|err| Error::new("failed to construct URL".to_string()).set_source(err))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::MockServer;
+    use crate::client::protocol::IngestData;
+    use crate::client::protocol::IngestType;
+
+    #[tokio::test]
+    async fn test_ingest_compression() {
+        for compression in [None, Some(Compression::Gzip), Some(Compression::Zstd)] {
+            let server = MockServer::start().await;
+            let http_client = reqwest::ClientBuilder::new().no_proxy().build().unwrap();
+            let client = Client::new(server.endpoint(), http_client)
+                .unwrap()
+                .with_compression(compression);
+            let request = IngestRequest {
+                ty: IngestType::Committed,
+                data: IngestData::Json {
+                    rows: "{\"a\":1}\n{\"a\":2}\n".to_string(),
+                },
+                statement: "INSERT INTO t".to_string(),
+            };
+            let expected = serde_json::to_string(&request).unwrap();
+            match client.ingest(&request).await.unwrap() {
+                Response::Success(result) => assert_eq!(result.num_rows_inserted, 2),
+                Response::Failed(err) => panic!("unexpected failure: {err}"),
+            }
+
+            let requests = server.requests();
+            assert_eq!(requests.len(), 1);
+            assert_eq!(
+                requests[0].content_encoding.as_deref(),
+                compression.map(|c| c.content_encoding())
+            );
+            assert_eq!(requests[0].body, expected);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A mock ScopeDB server for tests of ingestion.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+
+/// A request received by the mock server.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub content_encoding: Option<String>,
+    /// The decompressed body.
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Returns the rows of an ingest request.
+    pub fn rows(&self) -> String {
+        let request: serde_json::Value = serde_json::from_str(&self.body).unwrap();
+        request["data"]["rows"].as_str().unwrap().to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// The responses to the next requests, in order.
+    responses: VecDeque<MockResponse>,
+    /// Whether requests that have no scripted response fail with `503 Service Unavailable`.
+    unavailable: bool,
+    requests: Vec<MockRequest>,
+}
+
+/// A mock server that answers each ingest request with the next scripted response, or with a
+/// successful result inserting all rows of the request.
+///
+/// Each connection serves a single request.
+pub struct MockServer {
+    endpoint: String,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    tokio::spawn(serve(stream, state.clone()));
+                }
+            }
+        });
+        MockServer {
+            endpoint,
+            state,
+            task,
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Answers the next request without a scripted response with the given status, headers and
+    /// body.
+    pub fn respond(&self, status: u16, headers: &[(&str, &str)], body: &str) {
+        let response = MockResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.to_string(),
+        };
+        self.state.lock().unwrap().responses.push_back(response);
+    }
+
+    /// Fails requests without a scripted response with `503 Service Unavailable` while
+    /// `unavailable` is true.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state.lock().unwrap().unavailable = unavailable;
+    }
+
+    /// Returns the requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let mut request = vec![];
+    let header_end = loop {
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..n]);
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let header = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+    let header_value = |name: &str| {
+        header.lines().find_map(|line| {
+            let value = line.strip_prefix(name)?.strip_prefix(':')?;
+            Some(value.trim().to_string())
+        })
+    };
+    let content_encoding = header_value("content-encoding");
+    let content_length = header_value("content-length").map_or(0, |len| len.parse().unwrap());
+
+    let mut body = request[header_end..].to_vec();
+    let received = body.len();
+    body.resize(content_length, 0);
+    stream.read_exact(&mut body[received..]).await.unwrap();
+
+    let body = match content_encoding.as_deref() {
+        None => String::from_utf8(body).unwrap(),
+        Some("gzip") => {
+            let mut decoded = String::new();
+            let mut decoder = flate2::read::GzDecoder::new(body.as_slice());
+            decoder.read_to_string(&mut decoded).unwrap();
+            decoded
+        }
+        Some("zstd") => String::from_utf8(zstd::decode_all(body.as_slice()).unwrap()).unwrap(),
+        Some(encoding) => panic!("unexpected content encoding: {encoding}"),
+    };
+    let request = MockRequest {
+        content_encoding,
+        body,
+    };
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        let response = match state.responses.pop_front() {
+            Some(response) => response,
+            None if state.unavailable => MockResponse {
+                status: 503,
+                headers: vec![],
+                body: r#"{"message":"unavailable"}"#.to_string(),
+            },
+            None => {
+                let num_rows = serde_json::from_str::<serde_json::Value>(&request.body)
+                    .ok()
+                    .and_then(|body| Some(body["data"]["rows"].as_str()?.lines().count()))
+                    .unwrap_or_default();
+                MockResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: format!(r#"{{"num_rows_inserted":{num_rows}}}"#),
+                }
+            }
+        };
+        state.requests.push(request);
+        response
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(response.body.as_bytes()).await.unwrap();
+}
@@ -19,29 +19,65 @@ use exn::ResultExt;
 use exn::bail;
 use jiff::SignedDuration;
 use nu_ansi_term::Color;
+use reqwest::StatusCode;
 use uuid::Uuid;
 
 use crate::Error;
 use crate::client::connection::Client;
+pub use crate::client::connection::Compression;
+pub use crate::client::protocol::DataType;
 use crate::client::protocol::IngestData;
 use crate::client::protocol::IngestRequest;
 use crate::client::protocol::IngestResult;
-use crate::client::protocol::IngestType;
-use crate::client::protocol::Response;
+pub use crate::client::protocol::IngestType;
+pub use crate::client::protocol::Response;
 use crate::client::protocol::ResultFormat;
 use crate::client::protocol::StatementCancelResult;
-use crate::client::protocol::StatementEstimatedProgress;
+pub use crate::client::protocol::StatementEstimatedProgress;
+pub use crate::client::protocol::StatementProgress;
 use crate::client::protocol::StatementRequest;
 use crate::client::protocol::StatementRequestParams;
 use crate::client::protocol::StatementStatus;
+use crate::client::protocol::StatementStatusFinished;
 use crate::client::result::ResultSet;
 use crate::client::result::Value;
 use crate::pretty::pretty_print;
 
 mod connection;
+#[cfg(test)]
+pub mod mock;
 mod protocol;
 mod result;
 
+/// The outcome of a statement that has been executed on the server.
+#[derive(Debug, Clone)]
+pub enum StatementOutput {
+    /// The statement finished with the formatted result set.
+    Finished(String),
+    /// The statement failed with the error message.
+    Failed(String),
+    /// The statement was cancelled with the message.
+    Cancelled(String),
+}
+
+impl std::fmt::Display for StatementOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatementOutput::Finished(output)
+            | StatementOutput::Failed(output)
+            | StatementOutput::Cancelled(output) => write!(f, "{output}"),
+        }
+    }
+}
+
+/// The initial delay before retrying a throttled request without a `Retry-After` header.
+const MIN_THROTTLE_BACKOFF: Duration = Duration::from_secs(1);
+/// The maximum delay before retrying a throttled request, even if the `Retry-After` header
+/// requests a longer one.
+const MAX_THROTTLE_BACKOFF: Duration = Duration::from_secs(30);
+/// The maximum number of retries of a throttled request before giving up.
+const MAX_THROTTLE_RETRIES: u32 = 10;
+
 #[derive(Debug)]
 pub struct ScopeQLClient {
     client: Client,
@@ -52,6 +88,45 @@ fn format_result_set(
     duration: SignedDuration,
     progress: StatementEstimatedProgress,
 ) -> Result<String, Error> {
+    let table = format_result_table(result_set, false)?;
+
+    let queue_secs =
+        SignedDuration::from_nanos(progress.nanos_from_submitted - progress.nanos_from_started);
+    let run_secs = SignedDuration::from_nanos(progress.nanos_from_started);
+    let total_secs = duration;
+
+    let queue_secs = Color::LightCyan.paint(format!("{:.3}s", queue_secs.as_secs_f64()));
+    let run_secs = Color::LightCyan.paint(format!("{:.3}s", run_secs.as_secs_f64()));
+    let total_secs = Color::LightCyan.paint(format!("{:.3}s", total_secs.as_secs_f64()));
+
+    let queue = Color::LightGreen.paint("queue");
+    let run = Color::LightGreen.paint("run");
+    let total = Color::LightGreen.paint("total");
+
+    Ok(format!(
+        "{table}\ntime: {queue_secs} {queue} {run_secs} {run} {total_secs} {total}",
+    ))
+}
+
+/// Converts the status of a completed statement into its output, formatting the result set of a
+/// finished statement with `format_finished`.
+fn make_statement_output(
+    status: StatementStatus,
+    format_finished: impl FnOnce(&StatementStatusFinished) -> Result<String, Error>,
+) -> Result<StatementOutput, Error> {
+    match status {
+        StatementStatus::Finished(s) => Ok(StatementOutput::Finished(format_finished(&s)?)),
+        StatementStatus::Failed(s) => Ok(StatementOutput::Failed(s.message)),
+        StatementStatus::Cancelled(s) => Ok(StatementOutput::Cancelled(s.message)),
+        StatementStatus::Pending(_) | StatementStatus::Running(_) => {
+            unreachable!("statement must be completed")
+        }
+    }
+}
+
+/// Formats the result set as a table followed by the number of rows, with the type of each
+/// column under its name if `with_types` is true.
+fn format_result_table(result_set: ResultSet, with_types: bool) -> Result<String, Error> {
     let num_rows = match result_set.num_rows() {
         n @ 0..=1 => format!("({n} row)"),
         n => format!("({n} rows)"),
@@ -61,7 +136,10 @@ fn format_result_set(
         .schema()
         .fields()
         .iter()
-        .map(|f| f.name().to_string())
+        .map(|f| match with_types {
+            true => format!("{}\n{}", f.name(), f.data_type()),
+            false => f.name().to_string(),
+        })
         .collect::<Vec<_>>();
 
     let rows = result_set
@@ -99,22 +177,7 @@ fn format_result_set(
         table.add_row(row);
     }
 
-    let queue_secs =
-        SignedDuration::from_nanos(progress.nanos_from_submitted - progress.nanos_from_started);
-    let run_secs = SignedDuration::from_nanos(progress.nanos_from_started);
-    let total_secs = duration;
-
-    let queue_secs = Color::LightCyan.paint(format!("{:.3}s", queue_secs.as_secs_f64()));
-    let run_secs = Color::LightCyan.paint(format!("{:.3}s", run_secs.as_secs_f64()));
-    let total_secs = Color::LightCyan.paint(format!("{:.3}s", total_secs.as_secs_f64()));
-
-    let queue = Color::LightGreen.paint("queue");
-    let run = Color::LightGreen.paint("run");
-    let total = Color::LightGreen.paint("total");
-
-    Ok(format!(
-        "{table}\n{num_rows}\ntime: {queue_secs} {queue} {run_secs} {run} {total_secs} {total}",
-    ))
+    Ok(format!("{table}\n{num_rows}"))
 }
 
 impl ScopeQLClient {
@@ -129,45 +192,116 @@ impl ScopeQLClient {
         }
     }
 
+    /// Compresses the bodies of ingest and statement requests with the given encoding.
+    pub fn with_compression(self, compression: Option<Compression>) -> Self {
+        ScopeQLClient {
+            client: self.client.with_compression(compression),
+        }
+    }
+
     pub async fn load_jsonlines(
         &self,
         jsonlines: String,
         transform: String,
+        ty: IngestType,
     ) -> Result<IngestResult, Error> {
-        let data = IngestData::Json { rows: jsonlines };
-        let format = data.format();
-        let make_error = || Error::new(format!("failed to load {format} data: {transform}"));
-
+        let make_error = || Error::new(format!("failed to load json data: {transform}"));
         match self
-            .client
-            .ingest(IngestRequest {
-                ty: IngestType::Committed,
-                data,
-                statement: transform.clone(),
-            })
-            .await
-            .or_raise(make_error)?
+            .try_load_jsonlines(jsonlines, transform.clone(), ty)
+            .await?
         {
             Response::Success(result) => Ok(result),
+            Response::Failed(err) if err.code() == StatusCode::TOO_MANY_REQUESTS => {
+                Err(Error::new(format!(
+                    "the server is still throttling ingestion after {MAX_THROTTLE_RETRIES} retries: {err}"
+                )))
+                .or_raise(make_error)
+            }
             Response::Failed(err) => {
                 Err(Error::new(format!("fail to insert data: {err}"))).or_raise(make_error)
             }
         }
     }
 
+    /// Loads the rows like [`ScopeQLClient::load_jsonlines`], but returns the error status if
+    /// the server fails the request, so that a rejection can be told from an unreachable server.
+    pub async fn try_load_jsonlines(
+        &self,
+        jsonlines: String,
+        transform: String,
+        ty: IngestType,
+    ) -> Result<Response<IngestResult>, Error> {
+        let data = IngestData::Json { rows: jsonlines };
+        let format = data.format();
+        let make_error = || Error::new(format!("failed to load {format} data: {transform}"));
+        let request = IngestRequest {
+            ty,
+            data,
+            statement: transform.clone(),
+        };
+
+        // the server rejects requests with 429 when it is overloaded; retry after the requested
+        // delay, or with an exponential backoff if none is given
+        let mut backoff = MIN_THROTTLE_BACKOFF;
+        let mut retries = 0;
+        loop {
+            match self.client.ingest(&request).await.or_raise(make_error)? {
+                Response::Failed(err)
+                    if err.code() == StatusCode::TOO_MANY_REQUESTS
+                        && retries < MAX_THROTTLE_RETRIES =>
+                {
+                    retries += 1;
+                    let delay = err
+                        .retry_after()
+                        .unwrap_or(backoff)
+                        .min(MAX_THROTTLE_BACKOFF);
+                    log::warn!("the server is throttling ingestion; retrying in {delay:?}: {err}");
+                    tokio::time::sleep(delay).await;
+                    backoff = (backoff * 2).min(MAX_THROTTLE_BACKOFF);
+                }
+                response => return Ok(response),
+            }
+        }
+    }
+
     pub async fn execute_statement(
         &self,
         statement_id: Uuid,
         statement: String,
         display_progress: impl Fn(&'static str, StatementEstimatedProgress),
-    ) -> Result<String, Error> {
+    ) -> Result<StatementOutput, Error> {
+        let start_time = jiff::Timestamp::now();
+        let status = self
+            .run_statement(statement_id, statement, display_progress)
+            .await?;
+        make_statement_output(status, |s| {
+            let elapsed = start_time.duration_until(jiff::Timestamp::now());
+            format_result_set(s.result_set(), elapsed, s.progress.clone())
+        })
+    }
+
+    /// Executes the statement and returns the result set with the name and type of each column
+    /// in the header, but without timing.
+    pub async fn preview_statement(&self, statement: String) -> Result<StatementOutput, Error> {
+        let status = self
+            .run_statement(Uuid::now_v7(), statement, |_, _| {})
+            .await?;
+        make_statement_output(status, |s| format_result_table(s.result_set(), true))
+    }
+
+    /// Submits the statement and polls its status until it is finished, failed or cancelled.
+    async fn run_statement(
+        &self,
+        statement_id: Uuid,
+        statement: String,
+        display_progress: impl Fn(&'static str, StatementEstimatedProgress),
+    ) -> Result<StatementStatus, Error> {
         let make_error = || {
             Error::new(format!(
                 "failed to execute statement ({statement_id}): {statement}"
             ))
         };
 
-        let start_time = jiff::Timestamp::now();
         display_progress("Submitting", StatementEstimatedProgress::default());
 
         let mut status = match self
@@ -190,7 +324,7 @@ impl ScopeQLClient {
         };
 
         loop {
-            match status {
+            match &status {
                 StatementStatus::Pending(s) => {
                     display_progress("Pending", s.progress.clone());
                 }
@@ -198,14 +332,16 @@ impl ScopeQLClient {
                     display_progress("Running", s.progress.clone());
                 }
                 StatementStatus::Finished(s) => {
-                    let elapsed = start_time.duration_until(jiff::Timestamp::now());
-                    return format_result_set(s.result_set(), elapsed, s.progress.clone());
+                    display_progress("Finished", s.progress.clone());
+                    return Ok(status);
                 }
                 StatementStatus::Failed(s) => {
-                    return Ok(s.message.clone());
+                    display_progress("Failed", s.progress.clone());
+                    return Ok(status);
                 }
                 StatementStatus::Cancelled(s) => {
-                    return Ok(s.message.clone());
+                    display_progress("Cancelled", s.progress.clone());
+                    return Ok(status);
                 }
             }
 
@@ -243,3 +379,41 @@ impl ScopeQLClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_load_jsonlines_throttled() {
+        let server = MockServer::start().await;
+        let client = ScopeQLClient::new(server.endpoint().to_string());
+        let rows = "{\"a\":1}\n{\"a\":2}\n".to_string();
+        let transform = "INSERT INTO t".to_string();
+        let throttled = r#"{"message":"too many requests"}"#;
+
+        // retried after the delay requested by the server
+        server.respond(429, &[("retry-after", "0")], throttled);
+        let result = client
+            .load_jsonlines(rows.clone(), transform.clone(), IngestType::Committed)
+            .await
+            .unwrap();
+        assert_eq!(result.num_rows_inserted, 2);
+        assert_eq!(server.requests().len(), 2);
+
+        // given up after the maximum number of retries
+        for _ in 0..=MAX_THROTTLE_RETRIES {
+            server.respond(429, &[("retry-after", "0")], throttled);
+        }
+        let err = client
+            .load_jsonlines(rows, transform, IngestType::Committed)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("still throttling"));
+        assert_eq!(
+            server.requests().len(),
+            2 + MAX_THROTTLE_RETRIES as usize + 1
+        );
+    }
+}
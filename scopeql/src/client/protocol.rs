@@ -13,9 +13,11 @@
 // limitations under the License.
 
 use std::fmt;
+use std::time::Duration;
 
 use jiff::SignedDuration;
 use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
 use serde::Deserialize;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -39,6 +41,11 @@ impl<T: DeserializeOwned> Response<T> {
             let result = r.json().await.map_err(make_error)?;
             return Ok(Response::Success(result));
         }
+        let retry_after = r
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
 
         #[derive(Deserialize)]
         struct ErrorMessage {
@@ -47,18 +54,49 @@ impl<T: DeserializeOwned> Response<T> {
 
         let payload = r.bytes().await.map_err(make_error)?;
         if let Ok(ErrorMessage { message }) = serde_json::from_slice::<ErrorMessage>(&payload) {
-            Ok(Response::Failed(ErrorStatus { code, message }))
+            Ok(Response::Failed(ErrorStatus {
+                code,
+                message,
+                retry_after,
+            }))
         } else {
             let message = String::from_utf8_lossy(&payload).into_owned();
-            Ok(Response::Failed(ErrorStatus { code, message }))
+            Ok(Response::Failed(ErrorStatus {
+                code,
+                message,
+                retry_after,
+            }))
         }
     }
 }
 
+/// Parses the value of a `Retry-After` header, either in seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = jiff::fmt::rfc2822::parse(value).ok()?;
+    let delay = date.timestamp().duration_since(jiff::Timestamp::now());
+    Some(Duration::try_from(delay).unwrap_or(Duration::ZERO))
+}
+
 #[derive(Debug, Clone)]
 pub struct ErrorStatus {
     code: StatusCode,
     message: String,
+    /// The delay requested by the `Retry-After` header, if any.
+    retry_after: Option<Duration>,
+}
+
+impl ErrorStatus {
+    pub fn code(&self) -> StatusCode {
+        self.code
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
 }
 
 impl fmt::Display for ErrorStatus {
@@ -312,3 +350,38 @@ pub enum DataType {
     #[serde(rename = "null")]
     Null,
 }
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataType::Int => "int",
+            DataType::UInt => "uint",
+            DataType::Float => "float",
+            DataType::Binary => "binary",
+            DataType::String => "string",
+            DataType::Boolean => "boolean",
+            DataType::Timestamp => "timestamp",
+            DataType::Interval => "interval",
+            DataType::Array => "array",
+            DataType::Object => "object",
+            DataType::Any => "any",
+            DataType::Null => "null",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
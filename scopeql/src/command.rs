@@ -16,8 +16,18 @@ use std::path::PathBuf;
 
 use clap::ArgAction;
 use clap::ValueHint;
+use clap_complete::Shell;
 
-use crate::load::DataFormat;
+use crate::execute::EXIT_CODE_ERROR;
+use crate::execute::EXIT_CODE_STATEMENT_FAILED;
+use crate::execute::TimingFormat;
+use crate::load::CsvOptions;
+use crate::load::FollowOptions;
+use crate::load::FormatOptions;
+use crate::load::InferOptions;
+use crate::load::IngestOptions;
+use crate::load::RelayOptions;
+use crate::progress::ProgressMode;
 use crate::version::version;
 
 #[derive(Debug, clap::Parser)]
@@ -54,43 +64,164 @@ pub struct Args {
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum Subcommand {
     /// Run scopeql statements.
+    #[clap(after_long_help = RUN_EXIT_CODES)]
     Run {
-        /// The scopeql script file to run.
+        /// The scopeql script file to run; use `-` to read from stdin.
+        ///
+        /// If neither statements nor files are provided, the script is read from stdin when it
+        /// is piped.
         #[clap(group = "input", short, long, value_hint = ValueHint::FilePath, action = ArgAction::Append)]
         files: Vec<PathBuf>,
         /// The statements to run.
         #[clap(group = "input", action = ArgAction::Append)]
         statements: Vec<String>,
+        /// Display the progress of running statements on stderr.
+        ///
+        /// Progress bars are displayed when stderr is a terminal; otherwise, progress is
+        /// logged periodically.
+        #[clap(long, value_enum, value_name = "MODE", num_args = 0..=1, default_value = "off", default_missing_value = "compact")]
+        progress: ProgressMode,
+        /// Report the timing of each statement and the whole run.
+        #[clap(long, value_enum, value_name = "FORMAT")]
+        timing: Option<TimingFormat>,
+        /// Continue running the remaining statements after a statement fails.
+        #[clap(long, overrides_with = "stop_on_error")]
+        continue_on_error: bool,
+        /// Stop running at the first failed statement. This is the default.
+        #[clap(long, overrides_with = "continue_on_error")]
+        stop_on_error: bool,
+        /// Print each statement before executing it.
+        #[clap(short, long)]
+        echo: bool,
+        /// Print statements with their spans and fingerprints without executing them.
+        ///
+        /// Statements are tokenized and split only; nothing is sent to the server. The output is
+        /// itself a valid script, with each statement preceded by a comment of its index, source,
+        /// byte span and fingerprint.
+        #[clap(long)]
+        dry_run: bool,
+        /// Wrap each script in a single transaction, i.e., `BEGIN ... END`.
+        #[clap(long)]
+        single_transaction: bool,
     },
     /// Perform a load operation of source with transformations.
     Load {
-        /// The file path to load the source from.
-        #[clap(short, long, value_hint = ValueHint::FilePath)]
-        file: PathBuf,
+        /// The file paths or glob patterns to load the source from, e.g., `logs/2026-*.json.gz`;
+        /// `-` reads from stdin and requires `--format`.
+        ///
+        /// Can be specified multiple times.
+        #[clap(short, long = "file", value_name = "FILE", required = true, action = clap::ArgAction::Append, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
         /// The transformation to apply during the load.
         #[clap(short, long)]
         transform: String,
-        /// The source data format.
-        #[clap(long, value_enum)]
-        format: Option<DataFormat>,
+        /// Run the transform over the first N rows without inserting them, and print the
+        /// resulting rows with their column types.
+        ///
+        /// The rows are sent in a `VALUES` clause followed by the transform without its
+        /// `INSERT INTO` clause.
+        #[clap(long, value_name = "N", num_args = 0..=1, default_missing_value = "10", conflicts_with_all = ["follow", "resume"])]
+        preview: Option<usize>,
+        #[clap(flatten)]
+        format_options: Box<FormatOptions>,
+        #[clap(flatten)]
+        ingest_options: IngestOptions,
+        #[clap(flatten)]
+        follow_options: FollowOptions,
+    },
+    /// Infer the schema of data files for loading.
+    ///
+    /// Prints the inferred columns with their types, null ratios and sample values; with
+    /// `--table`, also prints a `CREATE TABLE` statement and a matching transform to edit.
+    Infer {
+        /// The file paths or glob patterns to scan; `-` reads from stdin and requires
+        /// `--format`.
+        ///
+        /// Can be specified multiple times.
+        #[clap(short, long = "file", value_name = "FILE", required = true, action = clap::ArgAction::Append, value_hint = ValueHint::FilePath)]
+        files: Vec<PathBuf>,
+        #[clap(flatten)]
+        infer_options: InferOptions,
+        #[clap(flatten)]
+        format_options: Box<FormatOptions>,
+    },
+    /// Serve a local HTTP endpoint that receives rows and ingests them in batches.
+    ///
+    /// Rows are posted to `/ingest` as NDJSON, JSON arrays or CSV, selected by the
+    /// `Content-Type` header (`application/x-ndjson`, `application/json` or `text/csv`), and
+    /// optionally compressed with gzip or zstd. The relay responds with `202 Accepted` once the
+    /// rows are queued, and `GET /health` reports whether it is running.
+    ///
+    /// OTLP/HTTP exporters can send JSON-encoded logs and traces to `/v1/logs` and
+    /// `/v1/traces`, which are flattened as in the `otlp-json` format.
+    ServeIngest {
+        /// The transformation to apply during the load.
+        #[clap(short, long)]
+        transform: String,
+        #[clap(flatten)]
+        relay_options: RelayOptions,
+        #[clap(flatten)]
+        csv_options: CsvOptions,
+    },
+    /// Manage the config file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
     },
     /// Generate command-line interface utilities.
     #[clap(name = "gen")]
     Generate {
         /// Output file path (if not specified, output to stdout).
-        #[clap(short, long, value_hint = ValueHint::FilePath)]
+        ///
+        /// For man pages, the directory to write a page per command to (if not specified, the
+        /// current directory).
+        #[clap(short, long, global = true, value_hint = ValueHint::AnyPath)]
         output: Option<PathBuf>,
 
         /// The target to generate.
-        #[clap(value_enum)]
+        #[command(subcommand)]
         target: GenerateTarget,
     },
 }
 
-#[derive(Debug, Clone, clap::ValueEnum)]
+const RUN_EXIT_CODES: &str = const_format::formatcp!(
+    "Exit Codes:\n  0  All statements finished successfully.\n  {}  Failed to run the script, e.g., the script cannot be read or tokenized, or the server cannot be reached.\n  {}  One or more statements failed or were cancelled.",
+    EXIT_CODE_ERROR,
+    EXIT_CODE_STATEMENT_FAILED,
+);
+
+#[derive(Debug, Clone, clap::Subcommand)]
 pub enum GenerateTarget {
     /// Generate the default config file.
     Config,
+    /// Generate the JSON Schema of the config file, for editors to validate and complete it.
+    ///
+    /// For example, with taplo or the Even Better TOML extension of VS Code, add
+    /// `#:schema ./scopeql.schema.json` at the top of the config file.
+    ConfigSchema,
+    /// Generate the completion script for a shell.
+    ///
+    /// For example, `scopeql gen completion bash > /etc/bash_completion.d/scopeql`.
+    Completion {
+        /// The shell to generate the completion script for.
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+    /// Generate the man pages of scopeql and every subcommand, e.g., `scopeql-load.1`.
+    Man,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum ConfigCommand {
+    /// Check the config file for syntax errors, unknown keys and invalid values.
+    ///
+    /// Problems are reported with their line and column; exits with 1 if any is found.
+    Validate {
+        /// The config file to check; defaults to `--config-file` or the first config file found
+        /// in the default locations.
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: Option<PathBuf>,
+    },
 }
 
 fn styled() -> clap::builder::Styles {
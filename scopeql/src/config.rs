@@ -14,35 +14,49 @@
 
 use std::collections::BTreeMap;
 use std::path::Path;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use serde::de::IntoDeserializer;
 use toml_edit::DocumentMut;
 
+use crate::client::Compression;
+
+/// The default locations of the config file, in the order they are looked up.
+fn default_config_files() -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    if let Some(home_dir) = dirs::home_dir() {
+        candidates.push(home_dir.join(".scopeql").join("config.toml"));
+        candidates.push(home_dir.join(".config").join("scopeql").join("config.toml"));
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        candidates.push(config_dir.join("scopeql").join("config.toml"));
+    }
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
 pub fn load_config<P: AsRef<Path>>(config_file: Option<P>) -> Config {
     // Layer 0: the config file
     let content = if let Some(file) = config_file.as_ref().map(AsRef::as_ref) {
-        std::fs::read_to_string(file).unwrap_or_else(|err| {
+        let content = std::fs::read_to_string(file).unwrap_or_else(|err| {
             let file = file.display();
             panic!("failed to read config file {file}: {err}")
-        })
+        });
+        warn_unknown_keys(file, &content);
+        content
     } else {
-        let mut candidates = vec![];
-        if let Some(home_dir) = dirs::home_dir() {
-            candidates.push(home_dir.join(".scopeql").join("config.toml"));
-            candidates.push(home_dir.join(".config").join("scopeql").join("config.toml"));
-        }
-        if let Some(config_dir) = dirs::config_dir() {
-            candidates.push(config_dir.join("scopeql").join("config.toml"));
-        }
-        candidates.sort();
-        candidates.dedup();
-
-        candidates
+        default_config_files()
             .into_iter()
-            .find_map(|candidate| std::fs::read_to_string(candidate).ok())
+            .find_map(|candidate| {
+                let content = std::fs::read_to_string(&candidate).ok()?;
+                warn_unknown_keys(&candidate, &content);
+                Some(content)
+            })
             .unwrap_or_else(|| {
                 toml::to_string(&Config::default()).expect("failed to serialize default config")
             })
@@ -94,16 +108,29 @@ pub fn load_config<P: AsRef<Path>>(config_file: Option<P>) -> Config {
             continue;
         }
 
+        if k.starts_with("connections_") && k.ends_with("_compression") {
+            let prefix_len = "connections_".len();
+            let suffix_len = "_compression".len();
+            let name = &k[prefix_len..k.len() - suffix_len];
+            let value = toml_edit::value(v.to_lowercase());
+            set_toml_path(&mut config, &["connections", name, "compression"], value);
+            continue;
+        }
+
         log::warn!("ignore unknown environment variable {k} with value {v}");
     }
 
     Config::deserialize(config.into_deserializer()).expect("failed to deserialize config")
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The configuration of scopeql, read from `config.toml`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
+    /// The name of the connection to use.
     default_connection: String,
 
+    /// The connections to ScopeDB servers by name.
     #[serde(default)]
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     connections: BTreeMap<String, ConnectionSpec>,
@@ -127,19 +154,288 @@ impl Default for Config {
                 "default".to_string(),
                 ConnectionSpec {
                     endpoint: "http://127.0.0.1:6543".to_string(),
+                    compression: None,
                 },
             )]),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A connection to a ScopeDB server.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct ConnectionSpec {
+    /// The HTTP endpoint of the server, e.g., `http://127.0.0.1:6543`.
     endpoint: String,
+
+    /// The content encoding of request bodies, `gzip` or `zstd`; uncompressed if not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compression: Option<Compression>,
 }
 
 impl ConnectionSpec {
     pub fn endpoint(&self) -> &str {
         &self.endpoint
     }
+
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+}
+
+/// Returns the JSON Schema of the config file, for editors to validate and complete it.
+pub fn config_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Config)).expect("config schema must be valid JSON")
+}
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The 1-based line and column of the problem, if known.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "line {line}, column {column}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Validates the config file, and prints the problems found; exits with an error if any.
+///
+/// The file is the given one, or the first of the default locations that exists.
+pub fn validate_config(config_file: Option<PathBuf>) {
+    let file = config_file.or_else(|| {
+        default_config_files()
+            .into_iter()
+            .find(|candidate| candidate.exists())
+    });
+    let Some(file) = file else {
+        log::error!("no config file found; specify one with --config-file");
+        std::process::exit(1);
+    };
+
+    let content = std::fs::read_to_string(&file).unwrap_or_else(|err| {
+        log::error!("failed to read config file {}: {err}", file.display());
+        std::process::exit(1);
+    });
+    let problems = check_config(&content);
+    if problems.is_empty() {
+        println!("{}: ok", file.display());
+        return;
+    }
+    for problem in &problems {
+        match problem.position {
+            Some((line, column)) => {
+                println!("{}:{line}:{column}: {}", file.display(), problem.message)
+            }
+            None => println!("{}: {}", file.display(), problem.message),
+        }
+    }
+    std::process::exit(1);
+}
+
+fn warn_unknown_keys(file: &Path, content: &str) {
+    let Ok(document) = toml_edit::Document::parse(content) else {
+        // syntax errors are reported when the config is parsed
+        return;
+    };
+    let mut problems = vec![];
+    check_unknown_keys(document.as_table(), content, &mut problems);
+    for problem in problems {
+        log::warn!("config file {}: {problem}", file.display());
+    }
+}
+
+/// Checks the content of a config file for syntax errors, unknown keys, values of wrong types,
+/// and an undefined default connection.
+fn check_config(content: &str) -> Vec<ConfigProblem> {
+    let document = match toml_edit::Document::parse(content) {
+        Ok(document) => document,
+        Err(err) => {
+            return vec![ConfigProblem {
+                position: err.span().map(|span| line_column(content, span.start)),
+                message: err.message().trim_end().to_string(),
+            }];
+        }
+    };
+
+    let mut problems = vec![];
+    // unknown keys are ignored when the config is deserialized, so the values and the default
+    // connection are checked regardless
+    check_unknown_keys(document.as_table(), content, &mut problems);
+
+    match toml::from_str::<Config>(content) {
+        Ok(config) => {
+            if config.get_default_connection().is_none() {
+                let span = document
+                    .get("default_connection")
+                    .and_then(|item| item.span());
+                problems.push(ConfigProblem {
+                    position: span.map(|span| line_column(content, span.start)),
+                    message: format!(
+                        "default connection `{}` is not defined in `connections`",
+                        config.default_connection
+                    ),
+                });
+            }
+        }
+        Err(err) => problems.push(ConfigProblem {
+            position: err.span().map(|span| line_column(content, span.start)),
+            message: err.message().trim_end().to_string(),
+        }),
+    }
+    problems
+}
+
+/// Reports the keys of the document that are not defined in the config schema.
+fn check_unknown_keys(table: &toml_edit::Table, content: &str, problems: &mut Vec<ConfigProblem>) {
+    let schema = config_schema();
+    let defs = schema.get("$defs").cloned().unwrap_or_default();
+    check_table_keys(table, &schema, &defs, "", content, problems);
+}
+
+fn check_table_keys(
+    table: &dyn toml_edit::TableLike,
+    schema: &serde_json::Value,
+    defs: &serde_json::Value,
+    path: &str,
+    content: &str,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    let schema = resolve_schema(schema, defs);
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    let additional = schema.get("additionalProperties");
+
+    for (key, item) in table.iter() {
+        let key_path = match path {
+            "" => key.to_string(),
+            path => format!("{path}.{key}"),
+        };
+        let value_schema = match properties.and_then(|properties| properties.get(key)) {
+            Some(value_schema) => value_schema,
+            None => match additional {
+                Some(value_schema) if value_schema.is_object() => value_schema,
+                Some(serde_json::Value::Bool(false)) => {
+                    let expected = properties
+                        .map(|properties| {
+                            let keys = properties.keys();
+                            keys.map(|key| format!("`{key}`")).collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+                    let span = table.key(key).and_then(|key| key.span());
+                    problems.push(ConfigProblem {
+                        position: span.map(|span| line_column(content, span.start)),
+                        message: format!(
+                            "unknown key `{key_path}`; expected one of {}",
+                            expected.join(", ")
+                        ),
+                    });
+                    continue;
+                }
+                _ => continue,
+            },
+        };
+        if let Some(table) = item.as_table_like() {
+            check_table_keys(table, value_schema, defs, &key_path, content, problems);
+        }
+    }
+}
+
+/// Resolves a schema that refers to a definition, e.g., `{"$ref": "#/$defs/ConnectionSpec"}`.
+fn resolve_schema<'a>(
+    schema: &'a serde_json::Value,
+    defs: &'a serde_json::Value,
+) -> &'a serde_json::Value {
+    match schema
+        .get("$ref")
+        .and_then(|r| r.as_str())
+        .and_then(|r| r.strip_prefix("#/$defs/"))
+    {
+        Some(name) => defs.get(name).unwrap_or(schema),
+        None => schema,
+    }
+}
+
+/// Returns the 1-based line and column of the byte offset in the content.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(content: &str) -> Vec<String> {
+        check_config(content)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_check_config() {
+        let valid = toml::to_string(&Config::default()).unwrap();
+        assert_eq!(check(&valid), Vec::<String>::new());
+
+        let content = r#"default_connection = "default"
+timeout = 1
+
+[connections.default]
+endpont = "http://127.0.0.1:6543"
+"#;
+        assert_eq!(
+            check(content),
+            vec![
+                "line 2, column 1: unknown key `timeout`; expected one of `connections`, `default_connection`",
+                "line 5, column 1: unknown key `connections.default.endpont`; expected one of `compression`, `endpoint`",
+                "line 4, column 1: missing field `endpoint`",
+            ]
+        );
+
+        let content = r#"default_connection = "prod"
+
+[connections.default]
+endpoint = "http://127.0.0.1:6543"
+compression = "brotli"
+"#;
+        let problems = check(content);
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("line 5, column 15: unknown variant `brotli`"),
+            "{problems:?}"
+        );
+
+        // problems of values are reported along with unknown keys
+        let content = r#"default_connection = "prod"
+timeout = 1
+"#;
+        assert_eq!(
+            check(content),
+            vec![
+                "line 2, column 1: unknown key `timeout`; expected one of `connections`, `default_connection`",
+                "line 1, column 22: default connection `prod` is not defined in `connections`",
+            ]
+        );
+
+        let content = "default_connection = \"prod\"\n";
+        assert_eq!(
+            check(content),
+            vec!["line 1, column 22: default connection `prod` is not defined in `connections`"]
+        );
+
+        let problems = check("default_connection = \n");
+        assert!(
+            problems[0].starts_with("line 1, column 22: "),
+            "{problems:?}"
+        );
+    }
 }
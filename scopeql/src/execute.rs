@@ -12,34 +12,118 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::BufRead;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use jiff::SignedDuration;
+use mea::latch::Latch;
 use scopeql_parser::TokenKind;
+use serde::Serialize;
+use uuid::Uuid;
 
 use crate::client::ScopeQLClient;
+use crate::client::StatementEstimatedProgress;
+use crate::client::StatementOutput;
 use crate::config::Config;
 use crate::global;
+use crate::progress::ProgressMode;
+use crate::progress::ProgressView;
+use crate::tokenizer::Token;
+use crate::tokenizer::fingerprint;
 use crate::tokenizer::run_tokenizer;
 
-pub fn execute(config: &Config, stmts: String) {
-    let endpoint = config
-        .get_default_connection()
-        .expect("no default connection in config");
-    let endpoint = endpoint.endpoint().to_owned();
-    let client = ScopeQLClient::new(endpoint);
+/// Exit code when `scopeql` fails to run a script, e.g., the script cannot be read or
+/// tokenized, or the server cannot be reached.
+pub const EXIT_CODE_ERROR: i32 = 1;
+/// Exit code when all statements are sent, but one or more of them failed or were cancelled.
+pub const EXIT_CODE_STATEMENT_FAILED: i32 = 3;
 
-    let tokens = match run_tokenizer(&stmts) {
-        Ok(tokens) => tokens,
-        Err(err) => {
-            log::error!("failed to parse statements: {err:?}");
-            std::process::exit(1);
-        }
-    };
+/// The output format of the timing report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimingFormat {
+    /// Log the timing of each statement and the whole run.
+    Text,
+    /// Print the timing of each statement and the whole run as a JSON document.
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecuteOptions {
+    /// Display the progress of running statements on stderr.
+    pub progress: ProgressMode,
+    /// Report the timing of statements if specified.
+    pub timing: Option<TimingFormat>,
+    /// Continue running the remaining statements after a statement fails.
+    pub continue_on_error: bool,
+    /// Print each statement before executing it.
+    pub echo: bool,
+    /// Print statements with their spans and fingerprints without executing them.
+    pub dry_run: bool,
+    /// Wrap each script in a single transaction.
+    pub single_transaction: bool,
+}
+
+#[derive(Debug, Clone)]
+struct StatementFailure {
+    statement: Option<String>,
+    message: String,
+}
+
+#[derive(Debug, Clone)]
+struct ScriptReport {
+    source: String,
+    /// The number of statements in the script, executed or not.
+    num_statements: usize,
+    num_executed: usize,
+    failures: Vec<StatementFailure>,
+}
 
+#[derive(Debug, Clone, Serialize)]
+struct StatementTiming {
+    statement_id: Uuid,
+    statement: String,
+    status: String,
+    queue_secs: f64,
+    run_secs: f64,
+    total_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TimingReport {
+    statements: Vec<StatementTiming>,
+    queue_secs: f64,
+    run_secs: f64,
+    total_secs: f64,
+}
+
+pub struct Executor {
+    /// The client to execute statements with, or `None` in dry-run mode.
+    client: Option<ScopeQLClient>,
+    options: ExecuteOptions,
+    timings: Vec<StatementTiming>,
+    scripts: Vec<ScriptReport>,
+    has_error: bool,
+    stopped: bool,
+    /// Whether any statement or script was not executed because of stopping.
+    skipped: bool,
+    start_time: jiff::Timestamp,
+}
+
+/// Splits tokens into complete statements.
+///
+/// Returns the ranges of complete statements, and the start of the outstanding statement that
+/// is not terminated by a semicolon, if any. Empty statements are skipped.
+fn split_statements(tokens: &[Token]) -> (Vec<Range<usize>>, Option<usize>) {
     let mut stmts_range = vec![];
-    let mut start = 0;
+    let mut start = None;
     let mut in_transaction = false;
-    let mut in_statement = true;
 
-    for token in &tokens {
+    for token in tokens {
         // transactions
         match token.kind {
             TokenKind::BEGIN => in_transaction = true,
@@ -49,43 +133,571 @@ pub fn execute(config: &Config, stmts: String) {
 
         // semicolons
         match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::EOI => {}
             TokenKind::SemiColon => {
-                if in_statement && !in_transaction {
-                    let end = token.span.start;
-                    stmts_range.push(start..end);
-                    start = token.span.end;
-                    in_statement = false;
+                if !in_transaction && let Some(start) = start.take() {
+                    stmts_range.push(start..token.span.start);
                 }
             }
             _ => {
-                if !in_statement {
-                    start = token.span.start;
-                    in_statement = true;
+                if start.is_none() {
+                    start = Some(token.span.start);
                 }
             }
         }
     }
 
-    if start < stmts.len() {
-        stmts_range.push(start..stmts.len());
+    (stmts_range, start)
+}
+
+impl Executor {
+    pub fn new(config: &Config, options: ExecuteOptions) -> Self {
+        // a dry run prints statements without connecting to the server
+        let client = (!options.dry_run).then(|| {
+            let connection = config
+                .get_default_connection()
+                .expect("no default connection in config");
+            ScopeQLClient::new(connection.endpoint().to_owned())
+                .with_compression(connection.compression())
+        });
+
+        Self {
+            client,
+            options,
+            timings: vec![],
+            scripts: vec![],
+            has_error: false,
+            stopped: false,
+            skipped: false,
+            start_time: jiff::Timestamp::now(),
+        }
+    }
+
+    pub fn execute_file(&mut self, file: &Path) {
+        if self.skip_if_stopped() {
+            return;
+        }
+
+        if file == Path::new("-") {
+            self.execute_stdin();
+            return;
+        }
+
+        let source = file.display().to_string();
+        match std::fs::read_to_string(file) {
+            Ok(content) => self.execute(source, content),
+            Err(err) => {
+                log::error!("failed to read script file {source}: {err}");
+                self.record_error(source, None, format!("failed to read script file: {err}"));
+            }
+        }
     }
 
-    if stmts_range.is_empty() {
-        log::info!("no statements provided");
-        return;
+    /// Executes statements read from stdin as soon as each of them is complete.
+    pub fn execute_stdin(&mut self) {
+        if self.skip_if_stopped() {
+            return;
+        }
+
+        let mut stdin = std::io::stdin().lock();
+        if self.options.single_transaction {
+            // the whole script must be read before it can be wrapped in a transaction
+            let mut content = String::new();
+            match stdin.read_to_string(&mut content) {
+                Ok(_) => self.execute("<stdin>".to_string(), content),
+                Err(err) => {
+                    log::error!("failed to read script from stdin: {err}");
+                    let message = format!("failed to read stdin: {err}");
+                    self.record_error("<stdin>".to_string(), None, message);
+                }
+            }
+            return;
+        }
+
+        self.scripts.push(ScriptReport {
+            source: "<stdin>".to_string(),
+            num_statements: 0,
+            num_executed: 0,
+            failures: vec![],
+        });
+
+        let mut buffer = String::new();
+        let mut consumed = 0;
+        loop {
+            let eof = match stdin.read_line(&mut buffer) {
+                Ok(n) => n == 0,
+                Err(err) => {
+                    log::error!("failed to read script from stdin: {err}");
+                    self.has_error = true;
+                    self.record_failure(None, format!("failed to read stdin: {err}"));
+                    return;
+                }
+            };
+
+            let tokens = match run_tokenizer(&buffer) {
+                Ok(tokens) => tokens,
+                // the outstanding statement may be incomplete, e.g., in a multi-line string
+                Err(_) if !eof => continue,
+                Err(err) => {
+                    log::error!("failed to parse statements: {err:?}");
+                    self.has_error = true;
+                    self.record_failure(None, format!("failed to parse statements: {err}"));
+                    return;
+                }
+            };
+
+            let (mut stmts_range, outstanding) = split_statements(&tokens);
+            if eof && let Some(start) = outstanding {
+                stmts_range.push(start..buffer.len());
+            }
+            if let Some(script) = self.scripts.last_mut() {
+                script.num_statements += stmts_range.len();
+            }
+            for range in stmts_range {
+                if self.skip_if_stopped() {
+                    return;
+                }
+                let span = consumed + range.start..consumed + range.end;
+                self.execute_statement(buffer[range].to_string(), span);
+            }
+
+            if eof {
+                return;
+            }
+            let end = outstanding.unwrap_or(buffer.len());
+            buffer.drain(..end);
+            consumed += end;
+        }
+    }
+
+    pub fn execute(&mut self, source: String, stmts: String) {
+        if self.skip_if_stopped() {
+            return;
+        }
+
+        let tokens = match run_tokenizer(&stmts) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                log::error!("failed to parse statements: {err:?}");
+                self.record_error(source, None, format!("failed to parse statements: {err}"));
+                return;
+            }
+        };
+
+        let (mut stmts_range, outstanding) = split_statements(&tokens);
+        if let Some(start) = outstanding {
+            stmts_range.push(start..stmts.len());
+        }
+
+        if stmts_range.is_empty() {
+            log::info!("no statements provided");
+            return;
+        }
+
+        if self.options.single_transaction {
+            if tokens.iter().any(|token| token.kind == TokenKind::BEGIN) {
+                log::error!("failed to wrap {source} in a single transaction: it contains BEGIN");
+                let message = "cannot wrap transaction blocks in a single transaction".to_string();
+                self.record_error(source, None, message);
+                return;
+            }
+
+            let mut transaction = "BEGIN\n".to_string();
+            for range in stmts_range {
+                transaction.push_str(stmts[range].trim());
+                transaction.push_str(";\n");
+            }
+            transaction.push_str("END");
+            // the transaction is executed, and reported, as a single statement
+            self.scripts.push(ScriptReport {
+                source,
+                num_statements: 1,
+                num_executed: 0,
+                failures: vec![],
+            });
+            self.execute_statement(transaction, 0..stmts.len());
+            return;
+        }
+
+        self.scripts.push(ScriptReport {
+            source,
+            num_statements: stmts_range.len(),
+            num_executed: 0,
+            failures: vec![],
+        });
+
+        for range in stmts_range {
+            if self.skip_if_stopped() {
+                break;
+            }
+            self.execute_statement(stmts[range.clone()].to_string(), range);
+        }
     }
 
-    for range in stmts_range {
-        let stmt = stmts[range].to_string();
-        let id = uuid::Uuid::now_v7();
+    /// Executes a statement, whose span is the byte range in its script.
+    fn execute_statement(&mut self, stmt: String, span: Range<usize>) {
+        let Some(script) = self.scripts.last_mut() else {
+            unreachable!("statements must be executed within a script");
+        };
+        script.num_executed += 1;
+
+        if self.options.dry_run {
+            // the statement is a substring of a tokenized script, or wrapped from such substrings
+            let tokens = run_tokenizer(&stmt).expect("statement must be tokenized");
+            println!(
+                "-- #{} {} {}..{} fingerprint: {:016x}\n{};",
+                script.num_executed,
+                script.source,
+                span.start,
+                span.end,
+                fingerprint(&tokens),
+                stmt.trim(),
+            );
+            return;
+        }
+
+        if self.options.echo {
+            println!("{};", stmt.trim());
+        }
+
+        let Some(client) = &self.client else {
+            unreachable!("statements must be executed with a client unless in dry-run mode");
+        };
+
+        let id = Uuid::now_v7();
         log::info!("executing statement {id}: {stmt}");
 
-        match global::rt().block_on(client.execute_statement(id, stmt, |_, _| ())) {
-            Ok(output) => log::info!("statement {id} results in:\n{output}"),
+        let start_time = jiff::Timestamp::now();
+        let view = ProgressView::new(self.options.progress);
+        let last_status = Arc::new(Mutex::new((
+            "Submitting",
+            StatementEstimatedProgress::default(),
+        )));
+        let stop_pb = Arc::new(Latch::new(1));
+
+        if self.options.progress != ProgressMode::Off {
+            global::rt().spawn({
+                let view = view.clone();
+                let stop_pb = stop_pb.clone();
+                async move {
+                    while stop_pb.try_wait().is_err() {
+                        tokio::time::sleep(Duration::from_millis(42)).await;
+                        view.tick();
+                    }
+                }
+            });
+        }
+
+        let output = global::rt().block_on(client.execute_statement(
+            id,
+            stmt.clone(),
+            |status, progress| {
+                view.update(status, &progress);
+                *last_status.lock().unwrap() = (status, progress);
+            },
+        ));
+
+        stop_pb.count_down();
+        let summary = view.finish();
+
+        let (status, progress) = last_status.lock().unwrap().clone();
+        let total = start_time.duration_until(jiff::Timestamp::now());
+
+        match output {
+            Ok(output) => {
+                if let Some(summary) = summary {
+                    log::info!("statement {id} {summary}");
+                }
+                match output {
+                    StatementOutput::Finished(output) => {
+                        log::info!("statement {id} results in:\n{output}");
+                    }
+                    StatementOutput::Failed(message) | StatementOutput::Cancelled(message) => {
+                        log::error!("statement {id} {}: {message}", status.to_lowercase());
+                        self.record_failure(Some(stmt.clone()), message);
+                    }
+                }
+                self.record_timing(id, stmt, status, progress, total);
+            }
             Err(err) => {
                 log::error!("failed to execute statement: {err:?}");
-                std::process::exit(1);
+                self.has_error = true;
+                self.record_failure(Some(stmt), format!("{err}"));
             }
         }
     }
+
+    /// Returns whether execution has stopped, recording that the remaining statements are
+    /// skipped if so.
+    fn skip_if_stopped(&mut self) -> bool {
+        self.skipped |= self.stopped;
+        self.stopped
+    }
+
+    fn record_error(&mut self, source: String, statement: Option<String>, message: String) {
+        self.has_error = true;
+        self.scripts.push(ScriptReport {
+            source,
+            num_statements: 0,
+            num_executed: 0,
+            failures: vec![],
+        });
+        self.record_failure(statement, message);
+    }
+
+    fn record_failure(&mut self, statement: Option<String>, message: String) {
+        let script = self
+            .scripts
+            .last_mut()
+            .expect("failures must be recorded within a script");
+        script
+            .failures
+            .push(StatementFailure { statement, message });
+        if !self.options.continue_on_error {
+            self.stopped = true;
+        }
+    }
+
+    fn record_timing(
+        &mut self,
+        statement_id: Uuid,
+        statement: String,
+        status: &'static str,
+        progress: StatementEstimatedProgress,
+        total: SignedDuration,
+    ) {
+        let Some(format) = self.options.timing else {
+            return;
+        };
+
+        let queue_secs =
+            SignedDuration::from_nanos(progress.nanos_from_submitted - progress.nanos_from_started)
+                .as_secs_f64();
+        let run_secs = SignedDuration::from_nanos(progress.nanos_from_started).as_secs_f64();
+        let total_secs = total.as_secs_f64();
+        let status = status.to_lowercase();
+
+        if format == TimingFormat::Text {
+            log::info!(
+                "statement {statement_id} {status}: queue {queue_secs:.3}s run {run_secs:.3}s total {total_secs:.3}s"
+            );
+        }
+
+        self.timings.push(StatementTiming {
+            statement_id,
+            statement,
+            status,
+            queue_secs,
+            run_secs,
+            total_secs,
+        });
+    }
+
+    /// Reports failures and the timing of the whole run, and returns the exit code.
+    pub fn finish(self) -> i32 {
+        let mut exit_code = 0;
+        for script in &self.scripts {
+            if script.failures.is_empty() {
+                continue;
+            }
+
+            exit_code = EXIT_CODE_STATEMENT_FAILED;
+            log::error!(
+                "{}: {} of {} statement(s) failed",
+                script.source,
+                script.failures.len(),
+                script.num_statements,
+            );
+            for failure in &script.failures {
+                match &failure.statement {
+                    Some(statement) => log::error!("  {}: {}", statement.trim(), failure.message),
+                    None => log::error!("  {}", failure.message),
+                }
+            }
+        }
+        if self.skipped {
+            log::error!("stopped at the first failure; remaining statements were not executed");
+        }
+        if self.has_error {
+            exit_code = EXIT_CODE_ERROR;
+        }
+
+        let Some(format) = self.options.timing else {
+            return exit_code;
+        };
+
+        let report = TimingReport {
+            queue_secs: self.timings.iter().map(|t| t.queue_secs).sum(),
+            run_secs: self.timings.iter().map(|t| t.run_secs).sum(),
+            total_secs: self
+                .start_time
+                .duration_until(jiff::Timestamp::now())
+                .as_secs_f64(),
+            statements: self.timings,
+        };
+
+        match format {
+            TimingFormat::Text => {
+                let TimingReport {
+                    statements,
+                    queue_secs,
+                    run_secs,
+                    total_secs,
+                } = report;
+                let n = statements.len();
+                log::info!(
+                    "executed {n} statement(s): queue {queue_secs:.3}s run {run_secs:.3}s total {total_secs:.3}s"
+                );
+            }
+            TimingFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            }
+        }
+
+        exit_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dry_run_executor(continue_on_error: bool) -> Executor {
+        let options = ExecuteOptions {
+            progress: ProgressMode::Off,
+            timing: None,
+            continue_on_error,
+            echo: false,
+            dry_run: true,
+            single_transaction: false,
+        };
+        Executor::new(&Config::default(), options)
+    }
+
+    fn split(source: &str) -> (Vec<&str>, Option<&str>) {
+        let tokens = run_tokenizer(source).unwrap();
+        let (stmts_range, outstanding) = split_statements(&tokens);
+        let stmts = stmts_range
+            .into_iter()
+            .map(|range| &source[range])
+            .collect();
+        (stmts, outstanding.map(|start| &source[start..]))
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split("SELECT 1; ;\n-- comment\nFROM t;"),
+            (vec!["SELECT 1", "FROM t"], None)
+        );
+        assert_eq!(
+            split("SELECT 1;\nFROM t\n"),
+            (vec!["SELECT 1"], Some("FROM t\n"))
+        );
+
+        // semicolons within a transaction block do not end statements
+        assert_eq!(
+            split("BEGIN; FROM a INSERT INTO b; FROM a DELETE; END; SELECT 2;"),
+            (
+                vec![
+                    "BEGIN; FROM a INSERT INTO b; FROM a DELETE; END",
+                    "SELECT 2"
+                ],
+                None
+            )
+        );
+        assert_eq!(
+            split("BEGIN; SELECT 1;"),
+            (vec![], Some("BEGIN; SELECT 1;"))
+        );
+
+        // a leading shebang line is a comment
+        assert_eq!(
+            split("#!/usr/bin/env -S scopeql run -f\nSELECT 1;"),
+            (vec!["SELECT 1"], None)
+        );
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let mut executor = dry_run_executor(false);
+        executor.execute("a".to_string(), "SELECT 1; SELECT 2;".to_string());
+        assert_eq!(executor.scripts[0].num_statements, 2);
+        assert_eq!(executor.scripts[0].num_executed, 2);
+        assert_eq!(executor.finish(), 0);
+
+        // a failure of the last statement skips nothing
+        let mut executor = dry_run_executor(false);
+        executor.execute("a".to_string(), "SELECT 1;".to_string());
+        executor.record_failure(Some("SELECT 1".to_string()), "failed".to_string());
+        assert!(executor.stopped);
+        assert!(!executor.skipped);
+
+        // a failure stops the remaining scripts
+        executor.execute("b".to_string(), "SELECT 2;".to_string());
+        assert!(executor.skipped);
+        assert_eq!(executor.scripts.len(), 1);
+        assert_eq!(executor.finish(), EXIT_CODE_STATEMENT_FAILED);
+
+        // a failure does not stop the remaining scripts with continue-on-error
+        let mut executor = dry_run_executor(true);
+        executor.execute("a".to_string(), "SELECT 1;".to_string());
+        executor.record_failure(Some("SELECT 1".to_string()), "failed".to_string());
+        executor.execute("b".to_string(), "SELECT 2;".to_string());
+        assert!(!executor.stopped);
+        assert_eq!(executor.scripts[1].num_executed, 1);
+        assert_eq!(executor.finish(), EXIT_CODE_STATEMENT_FAILED);
+
+        // an error of scopeql takes precedence over statement failures
+        let mut executor = dry_run_executor(true);
+        executor.execute("a".to_string(), "SELECT 1;".to_string());
+        executor.record_failure(Some("SELECT 1".to_string()), "failed".to_string());
+        let message = "failed to read script file".to_string();
+        executor.record_error("b".to_string(), None, message);
+        assert_eq!(executor.finish(), EXIT_CODE_ERROR);
+    }
+
+    #[test]
+    fn test_record_timing() {
+        let progress = StatementEstimatedProgress {
+            nanos_from_submitted: 3_500_000_000,
+            nanos_from_started: 1_250_000_000,
+            ..StatementEstimatedProgress::default()
+        };
+        let total = SignedDuration::from_millis(3600);
+
+        // timings are not recorded unless requested
+        let mut executor = dry_run_executor(false);
+        executor.record_timing(
+            Uuid::nil(),
+            "SELECT 1".to_string(),
+            "Finished",
+            progress.clone(),
+            total,
+        );
+        assert!(executor.timings.is_empty());
+
+        let mut executor = dry_run_executor(false);
+        executor.options.timing = Some(TimingFormat::Json);
+        executor.record_timing(
+            Uuid::nil(),
+            "SELECT 1".to_string(),
+            "Finished",
+            progress,
+            total,
+        );
+        let timing = serde_json::to_value(&executor.timings).unwrap();
+        assert_eq!(
+            timing,
+            serde_json::json!([{
+                "statement_id": "00000000-0000-0000-0000-000000000000",
+                "statement": "SELECT 1",
+                "status": "finished",
+                "queue_secs": 2.25,
+                "run_secs": 1.25,
+                "total_secs": 3.6,
+            }])
+        );
+        assert_eq!(executor.finish(), 0);
+    }
 }
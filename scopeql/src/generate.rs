@@ -0,0 +1,92 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use clap::CommandFactory;
+
+use crate::command::Command;
+use crate::command::GenerateTarget;
+use crate::config::Config;
+use crate::config::config_schema;
+
+/// Generates the target, and writes it to the output file, or stdout if not specified.
+pub fn generate(target: GenerateTarget, output: Option<PathBuf>) {
+    let content = match target {
+        GenerateTarget::Config => {
+            let config = Config::default();
+            let config = toml::to_string(&config).expect("default config must be always valid");
+            format!("{config}\n")
+        }
+        GenerateTarget::ConfigSchema => {
+            let schema = config_schema();
+            let schema = serde_json::to_string_pretty(&schema).expect("schema must be valid JSON");
+            format!("{schema}\n")
+        }
+        GenerateTarget::Completion { shell } => {
+            let mut cmd = Command::command();
+            let mut buf = vec![];
+            clap_complete::generate(shell, &mut cmd, "scopeql", &mut buf);
+            String::from_utf8(buf).expect("completion script must be valid UTF-8")
+        }
+        GenerateTarget::Man => {
+            // man pages are written to a directory, one per command
+            let dir = output.unwrap_or_else(|| PathBuf::from("."));
+            std::fs::create_dir_all(&dir)
+                .and_then(|_| clap_mangen::generate_to(Command::command(), &dir))
+                .unwrap_or_else(|err| {
+                    panic!("failed to write man pages to {}: {err}", dir.display())
+                });
+            return;
+        }
+    };
+
+    if let Some(output) = output {
+        std::fs::write(&output, content).unwrap_or_else(|err| {
+            let output = output.display();
+            let target = match target {
+                GenerateTarget::Config => "configurations",
+                GenerateTarget::ConfigSchema => "config schema",
+                GenerateTarget::Completion { .. } => "completion script",
+                GenerateTarget::Man => unreachable!("man pages are written above"),
+            };
+            panic!("failed to write {target} to {output}: {err}")
+        });
+    } else {
+        print!("{content}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap_complete::Shell;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_man() {
+        let dir = std::env::temp_dir().join(format!("scopeql-man-{}", std::process::id()));
+        generate(GenerateTarget::Man, Some(dir.clone()));
+        for page in ["scopeql.1", "scopeql-load.1", "scopeql-gen-completion.1"] {
+            let page = std::fs::read_to_string(dir.join(page)).unwrap();
+            assert!(page.starts_with(".ie"), "{page}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut buf = vec![];
+        clap_complete::generate(Shell::Bash, &mut Command::command(), "scopeql", &mut buf);
+        let script = String::from_utf8(buf).unwrap();
+        assert!(script.contains("serve-ingest"));
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::StreamReader;
+use arrow_json::LineDelimitedWriter;
+use arrow_schema::ArrowError;
+use arrow_schema::Schema;
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use indicatif::ProgressBar;
+use parquet::arrow::ProjectionMask;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde_json::Value;
+
+use crate::Error;
+
+/// The magic bytes at the beginning of an Arrow IPC file.
+const ARROW_FILE_MAGIC: &[u8] = b"ARROW1";
+/// The maximum alignment of the IPC file header, which is padded with zeros.
+const ARROW_FILE_MAX_ALIGNMENT: usize = 64;
+
+/// Reads the rows of a Parquet file.
+///
+/// Parquet files are read by row groups with random access, so the progress bar is advanced by
+/// the fraction of rows read instead of the bytes.
+pub fn read_parquet_rows(
+    file: &Path,
+    columns: &[String],
+    pb: ProgressBar,
+) -> Result<impl Iterator<Item = Result<Value, Error>> + use<>, Error> {
+    let make_error = || Error::new(format!("failed to read parquet file: {}", file.display()));
+
+    let reader = File::open(file).or_raise(make_error)?;
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(reader).or_raise(make_error)?;
+    if !columns.is_empty() {
+        let indices = projection(builder.schema(), columns)?;
+        let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+        builder = builder.with_projection(mask);
+    }
+
+    let total_rows = builder.metadata().file_metadata().num_rows().max(1) as u64;
+    let total_bytes = pb.length().unwrap_or(0);
+    let mut rows_read = 0;
+    let batches = builder.build().or_raise(make_error)?.inspect(move |batch| {
+        if let Ok(batch) = batch {
+            rows_read += batch.num_rows() as u64;
+            pb.set_position(total_bytes * rows_read / total_rows);
+        }
+    });
+    Ok(BatchRows::new(batches))
+}
+
+/// Reads the rows of an Arrow IPC file or stream.
+pub fn read_ipc_rows<R: BufRead>(
+    mut reader: R,
+    columns: &[String],
+) -> Result<impl Iterator<Item = Result<Value, Error>> + use<R>, Error> {
+    let make_error = || Error::new("failed to read arrow ipc data".to_string());
+
+    // The IPC file format wraps the stream format with a leading padded magic and a trailing
+    // footer, so that it can be read sequentially by skipping the header; the stream reader stops
+    // at the end-of-stream marker before the footer.
+    let header = reader.fill_buf().or_raise(make_error)?;
+    if header.starts_with(ARROW_FILE_MAGIC) {
+        let padding = header[ARROW_FILE_MAGIC.len()..]
+            .iter()
+            .take(ARROW_FILE_MAX_ALIGNMENT - ARROW_FILE_MAGIC.len())
+            .take_while(|b| **b == 0)
+            .count();
+        reader.consume(ARROW_FILE_MAGIC.len() + padding);
+    }
+
+    let reader = StreamReader::try_new(reader, None).or_raise(make_error)?;
+    let indices = match columns {
+        [] => None,
+        columns => Some(projection(&reader.schema(), columns)?),
+    };
+    let batches = reader.map(move |batch| match &indices {
+        None => batch,
+        Some(indices) => batch.and_then(|batch| batch.project(indices)),
+    });
+    Ok(BatchRows::new(batches))
+}
+
+/// Returns the indices of the projected columns in the schema.
+fn projection(schema: &Schema, columns: &[String]) -> Result<Vec<usize>, Error> {
+    let mut indices = Vec::with_capacity(columns.len());
+    for column in columns {
+        match schema.index_of(column) {
+            Ok(index) => indices.push(index),
+            Err(_) => bail!(Error::new(format!("column not found: {column}"))),
+        }
+    }
+    Ok(indices)
+}
+
+/// Converts record batches to JSON rows.
+///
+/// Timestamps with a time zone are written as RFC 3339 strings, and timestamps without one as
+/// ISO 8601 strings without an offset; structs, maps and lists are written as JSON objects and
+/// arrays, and null fields are omitted.
+struct BatchRows<I> {
+    batches: I,
+    rows: VecDeque<Value>,
+}
+
+impl<I> BatchRows<I> {
+    fn new(batches: I) -> Self {
+        Self {
+            batches,
+            rows: VecDeque::new(),
+        }
+    }
+}
+
+impl<I> Iterator for BatchRows<I>
+where
+    I: Iterator<Item = std::result::Result<RecordBatch, ArrowError>>,
+{
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rows.is_empty() {
+            let make_error = || Error::new("failed to read record batch".to_string());
+            let rows = self
+                .batches
+                .next()?
+                .or_raise(make_error)
+                .and_then(|batch| batch_to_rows(&batch).or_raise(make_error));
+            match rows {
+                Ok(rows) => self.rows.extend(rows),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        self.rows.pop_front().map(Ok)
+    }
+}
+
+fn batch_to_rows(batch: &RecordBatch) -> Result<Vec<Value>, Error> {
+    let make_error = || Error::new("failed to convert record batch to json".to_string());
+
+    let mut writer = LineDelimitedWriter::new(vec![]);
+    writer.write(batch).or_raise(make_error)?;
+    writer.finish().or_raise(make_error)?;
+    let buf = writer.into_inner();
+
+    serde_json::Deserializer::from_slice(&buf)
+        .into_iter::<Value>()
+        .map(|row| row.or_raise(make_error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::ArrayRef;
+    use arrow_array::Int64Array;
+    use arrow_array::ListArray;
+    use arrow_array::StringArray;
+    use arrow_array::StructArray;
+    use arrow_array::TimestampMicrosecondArray;
+    use arrow_array::types::Int64Type;
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::DataType;
+    use arrow_schema::Field;
+    use parquet::arrow::ArrowWriter;
+    use serde_json::json;
+
+    use super::*;
+
+    fn make_batch() -> RecordBatch {
+        let id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let ts: ArrayRef = Arc::new(
+            TimestampMicrosecondArray::from(vec![Some(1_735_689_600_000_000), None])
+                .with_timezone("UTC"),
+        );
+        let tags: ArrayRef = Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            Some(vec![]),
+        ]));
+        let host: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(Field::new("name", DataType::Utf8, true)),
+            Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+        )]));
+        RecordBatch::try_from_iter(vec![("id", id), ("ts", ts), ("tags", tags), ("host", host)])
+            .unwrap()
+    }
+
+    fn collect_rows(rows: impl Iterator<Item = Result<Value, Error>>) -> Vec<Value> {
+        rows.collect::<Result<Vec<_>, _>>().unwrap()
+    }
+
+    #[test]
+    fn test_read_ipc_rows() {
+        let batch = make_batch();
+        let mut writer = FileWriter::try_new(vec![], &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        let buf = writer.into_inner().unwrap();
+
+        let rows = collect_rows(read_ipc_rows(buf.as_slice(), &[]).unwrap());
+        assert_eq!(
+            rows,
+            vec![
+                json!({"id": 1, "ts": "2025-01-01T00:00:00Z", "tags": [1, 2], "host": {"name": "a"}}),
+                json!({"id": 2, "tags": [], "host": {"name": "b"}}),
+            ]
+        );
+
+        let columns = ["host".to_string(), "id".to_string()];
+        let rows = collect_rows(read_ipc_rows(buf.as_slice(), &columns).unwrap());
+        assert_eq!(rows[0], json!({"host": {"name": "a"}, "id": 1}));
+        assert!(read_ipc_rows(buf.as_slice(), &["x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_read_parquet_rows() {
+        let batch = make_batch();
+        let file = std::env::temp_dir().join(format!("scopeql-{}.parquet", std::process::id()));
+        let mut writer =
+            ArrowWriter::try_new(File::create(&file).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let columns = ["ts".to_string()];
+        let rows = read_parquet_rows(&file, &columns, ProgressBar::hidden()).map(collect_rows);
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(
+            rows.unwrap(),
+            vec![json!({"ts": "2025-01-01T00:00:00Z"}), json!({})]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use exn::Result;
+use exn::ResultExt;
+
+use crate::Error;
+
+/// A compression codec of a data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// Detects the compression codec by the file extension.
+    pub fn from_extension(file: &Path) -> Option<Self> {
+        match file.extension()?.to_str()? {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Returns the compression codec of an HTTP `Content-Encoding`, or `None` if the encoding is
+    /// not supported.
+    pub fn from_content_encoding(encoding: &str) -> Option<Self> {
+        match encoding.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "bzip2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Detects the compression codec by the magic bytes at the beginning of the data.
+    pub fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1F, 0x8B]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Some(Compression::Zstd)
+        } else if header.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else if header.starts_with(&[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00]) {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the path with the compression extension stripped, if any, e.g., `foo.json.gz` to
+/// `foo.json`; this is used for detecting the data format of compressed files.
+pub fn strip_extension(file: &Path) -> PathBuf {
+    match Compression::from_extension(file) {
+        Some(_) => file.with_extension(""),
+        None => file.to_path_buf(),
+    }
+}
+
+/// Wraps the reader with a decoder if the data is compressed.
+///
+/// The codec is detected by the file extension, falling back to the magic bytes.
+pub fn decompress<R: BufRead + Send + 'static>(
+    file: &Path,
+    mut reader: R,
+) -> Result<Box<dyn BufRead + Send>, Error> {
+    let compression = match Compression::from_extension(file) {
+        Some(compression) => Some(compression),
+        None => {
+            let header = reader
+                .fill_buf()
+                .or_raise(|| Error::new(format!("failed to read data file: {}", file.display())))?;
+            Compression::from_magic(header)
+        }
+    };
+    decompress_with(file, reader, compression)
+}
+
+/// Wraps the reader with a decoder of the given codec, if any.
+pub fn decompress_with<R: BufRead + Send + 'static>(
+    file: &Path,
+    reader: R,
+    compression: Option<Compression>,
+) -> Result<Box<dyn BufRead + Send>, Error> {
+    let reader: Box<dyn BufRead + Send> = match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        }
+        Some(Compression::Zstd) => {
+            let decoder = zstd::Decoder::with_buffer(reader).or_raise(|| {
+                Error::new(format!(
+                    "failed to decompress data file: {}",
+                    file.display()
+                ))
+            })?;
+            Box::new(BufReader::new(decoder))
+        }
+        Some(Compression::Bzip2) => {
+            Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader)))
+        }
+        Some(Compression::Xz) => Box::new(BufReader::new(
+            liblzma::bufread::XzDecoder::new_multi_decoder(reader),
+        )),
+    };
+    Ok(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_compression() {
+        let file = Path::new("logs/2025-01-01.json.gz");
+        assert_eq!(Compression::from_extension(file), Some(Compression::Gzip));
+        assert_eq!(strip_extension(file), Path::new("logs/2025-01-01.json"));
+        assert_eq!(strip_extension(Path::new("a.csv")), Path::new("a.csv"));
+
+        assert_eq!(
+            Compression::from_magic(b"\x1F\x8B\x08"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_magic(b"\x28\xB5\x2F\xFD"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::from_magic(b"BZh91AY"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(
+            Compression::from_magic(b"\xFD7zXZ\x00"),
+            Some(Compression::Xz)
+        );
+        assert_eq!(Compression::from_magic(b"{\"a\":1}"), None);
+    }
+
+    #[test]
+    fn test_decompress() {
+        use std::io::Read;
+        use std::io::Write;
+
+        let data = b"{\"a\":1}\n{\"a\":2}\n".repeat(100);
+        let gzip = {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(&data).unwrap();
+            encoder.finish().unwrap()
+        };
+        let zstd = zstd::encode_all(data.as_slice(), 0).unwrap();
+        let bzip2 = {
+            let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+            encoder.write_all(&data).unwrap();
+            encoder.finish().unwrap()
+        };
+        let xz = {
+            let mut encoder = liblzma::write::XzEncoder::new(vec![], 6);
+            encoder.write_all(&data).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let read = |file: &str, compressed: &[u8]| {
+            let reader = std::io::Cursor::new(compressed.to_vec());
+            let mut decoded = vec![];
+            decompress(Path::new(file), reader)
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            decoded
+        };
+        for (ext, compressed) in [("gz", &gzip), ("zst", &zstd), ("bz2", &bzip2), ("xz", &xz)] {
+            // detected by the file extension
+            assert_eq!(read(&format!("a.json.{ext}"), compressed), data, "{ext}");
+            // detected by the magic bytes
+            assert_eq!(read("-", compressed), data, "{ext}");
+        }
+        assert_eq!(read("a.json", &data), data);
+    }
+}
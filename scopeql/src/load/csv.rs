@@ -0,0 +1,539 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::Read;
+use std::str::FromStr;
+
+use csv::ByteRecord;
+use csv::Reader;
+use csv::ReaderBuilder;
+use csv::Trim;
+use exn::Result;
+use exn::ResultExt;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::Error;
+use crate::load::Row;
+use crate::load::reject::RejectedRow;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Number of records sampled to infer column types.
+const INFER_SAMPLE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "CSV Options")]
+pub struct CsvOptions {
+    /// Treat the first record as a regular record instead of the header.
+    ///
+    /// Columns are named `col_0`, `col_1`, ... if there is no header.
+    #[clap(long)]
+    pub no_header: bool,
+    /// Infer the type of each column from a sample of records.
+    ///
+    /// Integer, float, boolean and null values are loaded as JSON numbers, booleans and nulls
+    /// respectively; other values, including timestamps, are loaded as strings. Values that do
+    /// not match the inferred type of their column are loaded as strings. Numbers with a `+`
+    /// sign or leading zeros, e.g., zip codes like `007`, are kept as strings.
+    #[clap(long)]
+    pub infer_types: bool,
+    /// The field delimiter, e.g., `,`, `|` or `\t` (or `tab`) for TSV.
+    #[clap(long, value_name = "CHAR", default_value = ",", value_parser = parse_char)]
+    pub delimiter: u8,
+    /// The quote character.
+    #[clap(long, value_name = "CHAR", default_value = "\"", value_parser = parse_char)]
+    pub quote: u8,
+    /// The escape character for quotes in quoted fields, e.g., `\`.
+    ///
+    /// If not specified, quotes are escaped by doubling them, e.g., `""`.
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    pub escape: Option<u8>,
+    /// Skip records that start with the given character, e.g., `#`.
+    #[clap(long, value_name = "CHAR", value_parser = parse_char)]
+    pub comment: Option<u8>,
+    /// Trim leading and trailing whitespaces of fields and header names.
+    #[clap(long)]
+    pub trim: bool,
+    /// Allow records to have different numbers of fields.
+    #[clap(long)]
+    pub flexible: bool,
+    /// Load fields equal to the given string as null, e.g., `\N`; can be specified multiple
+    /// times.
+    ///
+    /// Defaults to the empty string if `--infer-types` is specified; otherwise, no fields are
+    /// loaded as null.
+    #[clap(long = "null", value_name = "STRING", action = clap::ArgAction::Append)]
+    pub nulls: Vec<String>,
+    /// The character encoding of the data.
+    ///
+    /// A leading UTF-8 byte order mark is always skipped.
+    #[clap(long, value_enum, value_name = "ENCODING", default_value = "utf8")]
+    pub encoding: Encoding,
+}
+
+impl Default for CsvOptions {
+    /// The options of `scopeql load` if no CSV option is specified.
+    fn default() -> Self {
+        CsvOptions {
+            no_header: false,
+            infer_types: false,
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            comment: None,
+            trim: false,
+            flexible: false,
+            nulls: vec![],
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+impl CsvOptions {
+    fn is_null(&self, field: &str) -> bool {
+        if self.nulls.is_empty() {
+            self.infer_types && field.is_empty()
+        } else {
+            self.nulls.iter().any(|null| null == field)
+        }
+    }
+}
+
+/// The character encoding of CSV data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    /// UTF-8; invalid sequences are errors.
+    Utf8,
+    /// UTF-8; invalid sequences are replaced with `U+FFFD`.
+    Utf8Lossy,
+    /// ISO-8859-1 (Latin-1).
+    Latin1,
+}
+
+impl Encoding {
+    fn decode(self, bytes: &[u8]) -> std::result::Result<String, std::str::Utf8Error> {
+        match self {
+            Encoding::Utf8 => std::str::from_utf8(bytes).map(str::to_string),
+            Encoding::Utf8Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            // Latin-1 code points are the first 256 Unicode code points
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+fn parse_char(s: &str) -> std::result::Result<u8, String> {
+    match s {
+        "\\t" | "tab" => Ok(b'\t'),
+        s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        s => Err(format!("expect a single ASCII character, got {s:?}")),
+    }
+}
+
+/// The type of a column inferred from sampled values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColumnType {
+    /// All sampled values are null.
+    Null,
+    Boolean,
+    Int,
+    Float,
+    Timestamp,
+    String,
+}
+
+impl ColumnType {
+    fn of(field: &str) -> ColumnType {
+        let is_number = is_canonical_number(field);
+        if field.eq_ignore_ascii_case("true") || field.eq_ignore_ascii_case("false") {
+            ColumnType::Boolean
+        } else if is_number && i64::from_str(field).is_ok() {
+            ColumnType::Int
+        } else if is_number && f64::from_str(field).is_ok_and(f64::is_finite) {
+            // NaN and infinities cannot be represented as JSON numbers
+            ColumnType::Float
+        } else if jiff::Timestamp::from_str(field).is_ok() {
+            ColumnType::Timestamp
+        } else {
+            ColumnType::String
+        }
+    }
+
+    /// Returns the narrowest type that both types can be represented as.
+    fn merge(self, other: ColumnType) -> ColumnType {
+        use ColumnType::*;
+
+        match (self, other) {
+            (Null, t) | (t, Null) => t,
+            (a, b) if a == b => a,
+            (Int, Float) | (Float, Int) => Float,
+            _ => String,
+        }
+    }
+
+    /// Converts the field into a JSON value of this type, falling back to a string if the field
+    /// cannot be represented as this type.
+    fn convert(self, field: &str) -> Value {
+        let value = match self {
+            ColumnType::Boolean => bool::from_str(&field.to_ascii_lowercase())
+                .ok()
+                .map(Value::Bool),
+            ColumnType::Int => i64::from_str(field)
+                .ok()
+                .filter(|_| is_canonical_number(field))
+                .map(Value::from),
+            ColumnType::Float => f64::from_str(field)
+                .ok()
+                .filter(|f| f.is_finite() && is_canonical_number(field))
+                .map(Value::from),
+            ColumnType::Null | ColumnType::Timestamp | ColumnType::String => None,
+        };
+        value.unwrap_or_else(|| Value::String(field.to_string()))
+    }
+}
+
+/// Returns whether the field starts like a number that is written the same way after it is
+/// converted, i.e., without a `+` sign or leading zeros; `007` or `+1` are likely zip codes or
+/// IDs, and are kept as strings.
+pub fn is_canonical_number(field: &str) -> bool {
+    let digits = field.strip_prefix('-').unwrap_or(field);
+    let mut chars = digits.chars();
+    match (chars.next(), chars.next()) {
+        (Some('0'), Some(c)) if c.is_ascii_digit() => false,
+        (Some(c), _) => c.is_ascii_digit(),
+        (None, _) => false,
+    }
+}
+
+/// Makes the keys of the JSON rows from the header, or `col_{i}` if no header.
+fn make_column_names(header: Option<&[String]>, num_columns: usize) -> Vec<String> {
+    let mut names = Vec::with_capacity(num_columns);
+    let mut seen = BTreeSet::new();
+    for i in 0..num_columns {
+        let name = match header
+            .and_then(|header| header.get(i))
+            .map(|name| name.trim())
+        {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => format!("col_{i}"),
+        };
+        // disambiguate duplicate names with column index, or a larger number if the name with
+        // the index is taken as well
+        let mut unique = name.clone();
+        let mut n = i;
+        while seen.contains(&unique) {
+            unique = format!("{name}_{n}");
+            n += 1;
+        }
+        seen.insert(unique.clone());
+        names.push(unique);
+    }
+    names
+}
+
+fn infer_column_types(
+    records: &[Vec<String>],
+    num_columns: usize,
+    is_null: impl Fn(&str) -> bool,
+) -> Vec<ColumnType> {
+    let mut types = vec![ColumnType::Null; num_columns];
+    for record in records.iter().take(INFER_SAMPLE_SIZE) {
+        for (i, field) in record.iter().enumerate().take(num_columns) {
+            if !is_null(field) {
+                types[i] = types[i].merge(ColumnType::of(field));
+            }
+        }
+    }
+    types
+}
+
+/// A reader of CSV records as JSON rows.
+pub(crate) struct CsvReader<R: Read> {
+    reader: Reader<R>,
+    options: CsvOptions,
+    names: Vec<String>,
+    types: Vec<ColumnType>,
+    /// Records read ahead for inferring column types, not yet emitted.
+    pending: VecDeque<std::result::Result<Vec<String>, RejectedRow>>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(mut reader: R, options: CsvOptions) -> Result<Self, Error> {
+        let make_error = || Error::new("failed to read csv header".to_string());
+
+        if reader
+            .fill_buf()
+            .or_raise(make_error)?
+            .starts_with(UTF8_BOM)
+        {
+            reader.consume(UTF8_BOM.len());
+        }
+
+        let reader = ReaderBuilder::new()
+            .has_headers(!options.no_header)
+            .delimiter(options.delimiter)
+            .quote(options.quote)
+            .escape(options.escape)
+            .double_quote(options.escape.is_none())
+            .comment(options.comment)
+            .trim(if options.trim { Trim::All } else { Trim::None })
+            .flexible(options.flexible)
+            .from_reader(reader);
+
+        let mut csv_reader = CsvReader {
+            reader,
+            options,
+            names: vec![],
+            types: vec![],
+            pending: VecDeque::new(),
+        };
+
+        let header = if csv_reader.options.no_header {
+            None
+        } else {
+            let header = csv_reader
+                .reader
+                .byte_headers()
+                .or_raise(make_error)?
+                .clone();
+            let header = csv_reader
+                .decode(&header)
+                .map_err(|err| Error::new(err.reason));
+            Some(header.or_raise(make_error)?)
+        };
+
+        // read ahead the sample, or the first record to determine the number of columns
+        let num_samples = if csv_reader.options.infer_types {
+            INFER_SAMPLE_SIZE
+        } else {
+            1
+        };
+        let mut num_sampled = 0;
+        while num_sampled < num_samples {
+            match csv_reader.read_record()? {
+                Some(record) => {
+                    num_sampled += record.is_ok() as usize;
+                    csv_reader.pending.push_back(record);
+                }
+                None => break,
+            }
+        }
+
+        let samples = csv_reader
+            .pending
+            .iter()
+            .filter_map(|record| record.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+        let num_columns = match &header {
+            Some(header) => header.len(),
+            None => samples.first().map_or(0, Vec::len),
+        };
+        csv_reader.names = make_column_names(header.as_deref(), num_columns);
+        csv_reader.types = if csv_reader.options.infer_types {
+            let options = &csv_reader.options;
+            infer_column_types(&samples, num_columns, |field| options.is_null(field))
+        } else {
+            vec![ColumnType::String; num_columns]
+        };
+
+        Ok(csv_reader)
+    }
+}
+
+impl<R: Read> CsvReader<R> {
+    fn decode(&self, record: &ByteRecord) -> std::result::Result<Vec<String>, RejectedRow> {
+        record
+            .iter()
+            .map(|field| self.options.encoding.decode(field))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|err| RejectedRow {
+                line: record.position().map(|pos| pos.line()),
+                reason: format!("failed to decode csv record: {err}"),
+                raw: Some(self.raw(record)),
+            })
+    }
+
+    /// Returns the fields of the record joined by the delimiter, for reporting.
+    fn raw(&self, record: &ByteRecord) -> String {
+        let delimiter = [self.options.delimiter];
+        let raw = record.iter().collect::<Vec<_>>().join(&delimiter[..]);
+        String::from_utf8_lossy(&raw).into_owned()
+    }
+
+    /// Reads the next record; a record that cannot be parsed or decoded is rejected, and
+    /// reading continues with the next record.
+    fn read_record(
+        &mut self,
+    ) -> Result<Option<std::result::Result<Vec<String>, RejectedRow>>, Error> {
+        let mut record = ByteRecord::new();
+        match self.reader.read_byte_record(&mut record) {
+            Ok(true) => Ok(Some(self.decode(&record))),
+            Ok(false) => Ok(None),
+            Err(err) if !err.is_io_error() => Ok(Some(Err(RejectedRow {
+                line: err.position().map(|pos| pos.line()),
+                reason: match err.kind() {
+                    csv::ErrorKind::UnequalLengths {
+                        expected_len, len, ..
+                    } => format!("expected {expected_len} fields, found {len}"),
+                    _ => format!("failed to parse csv record: {err}"),
+                },
+                raw: Some(self.raw(&record)),
+            }))),
+            Err(err) => Err(err).or_raise(|| Error::new("failed to read csv record".to_string())),
+        }
+    }
+
+    fn make_row(&self, record: Vec<String>) -> Value {
+        let mut row = Map::new();
+        for (i, field) in record.into_iter().enumerate() {
+            let value = if self.options.is_null(&field) {
+                Value::Null
+            } else {
+                match self.types.get(i) {
+                    Some(ty) => ty.convert(&field),
+                    None => Value::String(field),
+                }
+            };
+            let name = match self.names.get(i) {
+                Some(name) => name.clone(),
+                None => format!("col_{i}"),
+            };
+            row.insert(name, value);
+        }
+        Value::Object(row)
+    }
+}
+
+impl<R: Read> Iterator for CsvReader<R> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.pending.pop_front() {
+            Some(record) => record,
+            None => match self.read_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            },
+        };
+        Some(Ok(match record {
+            Ok(record) => Row::Valid(self.make_row(record)),
+            Err(rejected) => Row::Rejected(rejected),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_infer_column_types() {
+        let records = [
+            ["1", "1.5", "true", "2026-01-01T00:00:00Z", "", "a"],
+            ["2", "2", "FALSE", "2026-01-02T00:00:00Z", "\\N", "1"],
+            ["", "", "", "", "", ""],
+        ]
+        .map(|record| record.map(String::from).to_vec());
+        assert_eq!(
+            infer_column_types(&records, 6, |field| field.is_empty() || field == "\\N"),
+            vec![
+                ColumnType::Int,
+                ColumnType::Float,
+                ColumnType::Boolean,
+                ColumnType::Timestamp,
+                ColumnType::Null,
+                ColumnType::String,
+            ]
+        );
+
+        assert_eq!(ColumnType::Int.convert("42"), Value::from(42));
+        assert_eq!(ColumnType::Int.convert("x"), Value::from("x"));
+        assert_eq!(ColumnType::Boolean.convert("True"), Value::from(true));
+        assert_eq!(ColumnType::Float.convert("NaN"), Value::from("NaN"));
+        for field in ["NaN", "inf", "-infinity"] {
+            assert_eq!(ColumnType::of(field), ColumnType::String);
+        }
+
+        // leading zeros and signs would be lost if converted to numbers
+        for field in ["007", "+1", "-01", "00.5", "+1.5", ".5"] {
+            assert_eq!(ColumnType::of(field), ColumnType::String, "{field}");
+        }
+        for field in ["0", "-1", "10"] {
+            assert_eq!(ColumnType::of(field), ColumnType::Int, "{field}");
+        }
+        for field in ["0.5", "-0.5", "1e3"] {
+            assert_eq!(ColumnType::of(field), ColumnType::Float, "{field}");
+        }
+        assert_eq!(ColumnType::Int.convert("007"), Value::from("007"));
+        assert_eq!(ColumnType::Float.convert("+1.5"), Value::from("+1.5"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Encoding::Latin1.decode(b"caf\xE9").unwrap(), "café");
+        assert_eq!(
+            Encoding::Utf8Lossy.decode(b"caf\xE9").unwrap(),
+            "caf\u{FFFD}"
+        );
+        assert!(Encoding::Utf8.decode(b"caf\xE9").is_err());
+        assert_eq!(parse_char("\\t"), Ok(b'\t'));
+        assert_eq!(parse_char("|"), Ok(b'|'));
+        assert!(parse_char("ab").is_err());
+    }
+
+    #[test]
+    fn test_reject_records() {
+        let options = CsvOptions {
+            infer_types: true,
+            ..CsvOptions::default()
+        };
+        let data = b"id,name\n1,a\n2,b,extra\n3,caf\xE9\n4,d\n";
+        let rows = CsvReader::new(&data[..], options)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], Row::Valid(json!({"id": 1, "name": "a"})));
+        assert!(matches!(
+            &rows[1],
+            Row::Rejected(RejectedRow { line: Some(3), raw: Some(raw), .. }) if raw == "2,b,extra"
+        ));
+        assert!(matches!(
+            &rows[2],
+            Row::Rejected(RejectedRow { line: Some(4), .. })
+        ));
+        assert_eq!(rows[3], Row::Valid(json!({"id": 4, "name": "d"})));
+    }
+
+    #[test]
+    fn test_make_column_names() {
+        let header = ["id", "", "id"].map(String::from);
+        assert_eq!(
+            make_column_names(Some(&header[..]), 4),
+            vec!["id", "col_1", "id_2", "col_3"]
+        );
+        assert_eq!(make_column_names(None, 2), vec!["col_0", "col_1"]);
+        let header = ["id_2", "id", "id"].map(String::from);
+        assert_eq!(
+            make_column_names(Some(&header[..]), 3),
+            vec!["id_2", "id", "id_3"]
+        );
+    }
+}
@@ -0,0 +1,610 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+use std::fs::File;
+use std::fs::Metadata;
+use std::io::Cursor;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use indicatif::HumanCount;
+use indicatif::ProgressBar;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Error;
+use crate::client::ScopeQLClient;
+use crate::load::DataFormat;
+use crate::load::FormatOptions;
+use crate::load::IngestOptions;
+use crate::load::Row;
+use crate::load::RowReader;
+use crate::load::json::JsonLayout;
+use crate::load::json::read_json_rows;
+use crate::load::logs::LogFormat;
+use crate::load::logs::LogReader;
+use crate::load::otlp::read_otlp_rows;
+use crate::load::parse_duration;
+use crate::load::reject::RejectFile;
+use crate::load::throttle::RateLimiter;
+use crate::load::write_file_atomically;
+
+/// How often the file is polled for new lines, rotation and truncation.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Follow Options")]
+pub struct FollowOptions {
+    /// Keep reading lines appended to the file and ingest them continuously, like `tail -f`,
+    /// until interrupted.
+    ///
+    /// A rotated or truncated file is read again from the beginning. Only line-oriented formats
+    /// are supported, i.e., `ndjson` (or `json`), `logfmt`, `syslog`, `clf` and `regex`.
+    ///
+    /// Rotation is only detected on Unix; on other platforms, only truncation is detected.
+    #[clap(long, conflicts_with = "resume")]
+    pub follow: bool,
+    /// Persist the offset of the ingested lines in the given file, and resume from the offset
+    /// when the load is restarted.
+    ///
+    /// The checkpoint is written after each batch is ingested, so lines ingested right before
+    /// an abnormal exit may be ingested again when the load is restarted.
+    #[clap(long, value_name = "FILE", requires = "follow")]
+    pub checkpoint: Option<PathBuf>,
+    /// Maximum time to buffer new lines before ingesting them, e.g., `1s` or `500ms`.
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "1s",
+        value_parser = parse_duration,
+        requires = "follow"
+    )]
+    pub flush_interval: Duration,
+}
+
+/// The offset of the ingested lines in a followed file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Checkpoint {
+    /// The identity of the file, to detect a file rotated while the load is stopped.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_id: Option<(u64, u64)>,
+    offset: u64,
+}
+
+impl Checkpoint {
+    fn read(path: &Path) -> Result<Option<Checkpoint>, Error> {
+        let make_error = || Error::new(format!("failed to read checkpoint: {}", path.display()));
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content).or_raise(make_error)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).or_raise(make_error),
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let content = serde_json::to_string(self)
+            .or_raise(|| Error::new("failed to serialize checkpoint".to_string()))?;
+        write_file_atomically(path, content.as_bytes())
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// The file identity is not available on stable Rust for Windows, so a rotated file is not
+/// detected there.
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// A growing file being read line by line.
+struct Tail {
+    path: PathBuf,
+    file: File,
+    file_id: Option<(u64, u64)>,
+    /// The offset of the data read from the file.
+    offset: u64,
+    /// The trailing incomplete line.
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    fn open(path: &Path, checkpoint: Option<Checkpoint>) -> Result<Self, Error> {
+        let make_error = || Error::new(format!("failed to open data file: {}", path.display()));
+        let mut file = File::open(path).or_raise(make_error)?;
+        let metadata = file.metadata().or_raise(make_error)?;
+        let file_id = file_id(&metadata);
+
+        let offset = match checkpoint {
+            Some(checkpoint)
+                if checkpoint.file_id == file_id && checkpoint.offset <= metadata.len() =>
+            {
+                checkpoint.offset
+            }
+            Some(_) => {
+                log::warn!(
+                    "{} has been rotated or truncated since the checkpoint; reading from the beginning",
+                    path.display()
+                );
+                0
+            }
+            None => 0,
+        };
+        file.seek(SeekFrom::Start(offset)).or_raise(make_error)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            file_id,
+            offset,
+            partial: vec![],
+        })
+    }
+
+    /// Returns the offset after the last complete line.
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            file_id: self.file_id,
+            offset: self.offset - self.partial.len() as u64,
+        }
+    }
+
+    /// Reads the complete lines appended since the last read, at most `limit` bytes at a time
+    /// unless a single line is longer.
+    fn read_lines(&mut self, limit: usize) -> Result<Vec<u8>, Error> {
+        let make_error =
+            || Error::new(format!("failed to read data file: {}", self.path.display()));
+        let limit = limit.max(1);
+        loop {
+            let n = (&mut self.file)
+                .take(limit as u64)
+                .read_to_end(&mut self.partial)
+                .or_raise(make_error)?;
+            self.offset += n as u64;
+
+            match self.partial.iter().rposition(|b| *b == b'\n') {
+                Some(pos) => {
+                    let rest = self.partial.split_off(pos + 1);
+                    return Ok(std::mem::replace(&mut self.partial, rest));
+                }
+                // a line longer than the limit
+                None if n == limit => continue,
+                None => return Ok(vec![]),
+            }
+        }
+    }
+
+    /// Reopens the file if it has been rotated or truncated, and returns the incomplete last
+    /// line of a rotated file.
+    ///
+    /// A rotated file is only reopened once it has been read to the end by
+    /// [`Tail::read_lines`], so that lines appended right before the rotation are not lost.
+    fn reopen_if_rotated(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let make_error = || {
+            Error::new(format!(
+                "failed to reopen data file: {}",
+                self.path.display()
+            ))
+        };
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // the file has been moved and not yet recreated
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).or_raise(make_error),
+        };
+
+        if file_id(&metadata) != self.file_id {
+            // lines have been appended to the rotated file since the last read
+            let n = (&mut self.file)
+                .take(1)
+                .read_to_end(&mut self.partial)
+                .or_raise(make_error)?;
+            if n > 0 {
+                self.offset += n as u64;
+                return Ok(None);
+            }
+
+            log::info!("{} has been rotated; reopening", self.path.display());
+            self.file = File::open(&self.path).or_raise(make_error)?;
+            self.file_id = file_id(&self.file.metadata().or_raise(make_error)?);
+            self.offset = 0;
+            return Ok(Some(std::mem::take(&mut self.partial)));
+        }
+
+        if metadata.len() < self.offset {
+            log::info!(
+                "{} has been truncated; reading from the beginning",
+                self.path.display()
+            );
+            self.file.seek(SeekFrom::Start(0)).or_raise(make_error)?;
+            self.offset = 0;
+            self.partial.clear();
+            return Ok(Some(vec![]));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Rows read but not yet ingested.
+#[derive(Default)]
+struct Batch {
+    data: String,
+    rows: usize,
+    first_row_at: Option<Instant>,
+}
+
+impl Batch {
+    fn is_full(&self, options: &IngestOptions) -> bool {
+        self.rows >= options.batch_rows || self.data.len() >= options.batch_size
+    }
+}
+
+/// Follows the file and ingests new lines until interrupted, and returns the number of rows
+/// inserted.
+pub async fn follow(
+    client: &ScopeQLClient,
+    file: &Path,
+    format: DataFormat,
+    transform: String,
+    format_options: &FormatOptions,
+    ingest_options: &IngestOptions,
+    options: &FollowOptions,
+) -> Result<u64, Error> {
+    if !matches!(
+        format,
+        DataFormat::Json
+            | DataFormat::Ndjson
+            | DataFormat::Logfmt
+            | DataFormat::Syslog
+            | DataFormat::Clf
+            | DataFormat::Regex
+            | DataFormat::OtlpJson
+    ) {
+        bail!(Error::new(format!(
+            "--follow does not support the {format:?} format; only line-oriented formats are supported"
+        )));
+    }
+
+    let checkpoint = match &options.checkpoint {
+        Some(path) => Checkpoint::read(path)?,
+        None => None,
+    };
+    let mut tail = Tail::open(file, checkpoint)?;
+    let reject_file = match &ingest_options.error_options.reject_file {
+        Some(path) => Some(RejectFile::open(path.clone(), true)?),
+        None => None,
+    };
+
+    let pb = ProgressBar::new_spinner();
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb.set_message(format!("following {}", file.display()));
+
+    let mut batch = Batch::default();
+    let mut num_rows_inserted = 0;
+    let rate_limiter = RateLimiter::new(ingest_options);
+    let mut flush = async |batch: &mut Batch, checkpoint: &Checkpoint| -> Result<(), Error> {
+        if batch.rows > 0 {
+            let data = std::mem::take(&mut batch.data);
+            rate_limiter
+                .acquire(batch.rows as u64, data.len() as u64)
+                .await;
+            let ty = ingest_options.mode.into();
+            let result = client.load_jsonlines(data, transform.clone(), ty).await?;
+            num_rows_inserted += result.num_rows_inserted as u64;
+            pb.set_message(format!(
+                "following {}: {} rows ingested",
+                file.display(),
+                HumanCount(num_rows_inserted)
+            ));
+        }
+        *batch = Batch::default();
+        if let Some(path) = &options.checkpoint {
+            checkpoint.write(path)?;
+        }
+        Ok(())
+    };
+
+    // created once so that an interrupt received while reading or ingesting is not lost
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let start = tail.checkpoint();
+        let lines = tail.read_lines(ingest_options.batch_size)?;
+        if !lines.is_empty() {
+            let rows = read_rows(lines, format, format_options)?;
+            append_rows(
+                &mut batch,
+                rows,
+                file,
+                reject_file.as_ref(),
+                ingest_options,
+                &start,
+                &mut flush,
+            )
+            .await?;
+            if batch.is_full(ingest_options) {
+                flush(&mut batch, &tail.checkpoint()).await?;
+            }
+            continue;
+        }
+
+        let flush_due = batch
+            .first_row_at
+            .is_some_and(|t| t.elapsed() >= options.flush_interval);
+        if flush_due {
+            flush(&mut batch, &tail.checkpoint()).await?;
+        }
+
+        let start = tail.checkpoint();
+        if let Some(rest) = tail.reopen_if_rotated()? {
+            // the last line of a rotated file may not end with a newline
+            if !rest.is_empty() {
+                let rows = read_rows(rest, format, format_options)?;
+                append_rows(
+                    &mut batch,
+                    rows,
+                    file,
+                    reject_file.as_ref(),
+                    ingest_options,
+                    &start,
+                    &mut flush,
+                )
+                .await?;
+            }
+            flush(&mut batch, &tail.checkpoint()).await?;
+            continue;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = &mut ctrl_c => {
+                log::info!("interrupted; ingesting buffered rows");
+                flush(&mut batch, &tail.checkpoint()).await?;
+                break;
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+    Ok(num_rows_inserted)
+}
+
+/// Parses the lines into rows.
+fn read_rows(
+    lines: Vec<u8>,
+    format: DataFormat,
+    options: &FormatOptions,
+) -> Result<RowReader, Error> {
+    let reader = Cursor::new(lines);
+    let rows: RowReader = match format {
+        DataFormat::Json | DataFormat::Ndjson => {
+            read_json_rows(reader, JsonLayout::Lines, options.json_options.clone())?
+        }
+        DataFormat::Logfmt => Box::new(LogReader::new(reader, LogFormat::Logfmt)),
+        DataFormat::Syslog => Box::new(LogReader::new(reader, LogFormat::Syslog)),
+        DataFormat::Clf => Box::new(LogReader::new(reader, LogFormat::Clf)),
+        DataFormat::Regex => match &options.log_options.regex {
+            Some(regex) => Box::new(LogReader::new(reader, LogFormat::Regex(regex.clone()))),
+            None => bail!(Error::new(
+                "the regex format requires the --regex option".to_string()
+            )),
+        },
+        DataFormat::OtlpJson => read_otlp_rows(reader, JsonLayout::Lines)?,
+        _ => unreachable!("unsupported format for follow: {format:?}"),
+    };
+    Ok(rows)
+}
+
+/// Appends the rows to the batch, and flushes the batch whenever it is full; rows that cannot be
+/// parsed are skipped, and written to the reject file if any.
+///
+/// `checkpoint` is the offset before the rows, so that the rows not yet ingested are read again
+/// when the load is restarted after a flush in the middle of the rows.
+async fn append_rows(
+    batch: &mut Batch,
+    rows: RowReader,
+    file: &Path,
+    reject_file: Option<&RejectFile>,
+    options: &IngestOptions,
+    checkpoint: &Checkpoint,
+    flush: &mut impl AsyncFnMut(&mut Batch, &Checkpoint) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for row in rows {
+        match row? {
+            Row::Valid(row) => {
+                if batch.is_full(options) {
+                    flush(batch, checkpoint).await?;
+                }
+                writeln!(&mut batch.data, "{row}").unwrap();
+                batch.rows += 1;
+                batch.first_row_at.get_or_insert_with(Instant::now);
+            }
+            Row::Rejected(row) => {
+                log::warn!("skipped a line in {}: {row}", file.display());
+                if let Some(reject_file) = reject_file {
+                    reject_file.write(&file.display().to_string(), &row)?;
+                }
+            }
+        }
+    }
+    if let Some(reject_file) = reject_file {
+        reject_file.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_tail() {
+        let path = std::env::temp_dir().join(format!("scopeql-tail-{}.log", std::process::id()));
+        let mut writer = File::create(&path).unwrap();
+        writer.write_all(b"a=1\nb=").unwrap();
+
+        let mut tail = Tail::open(&path, None).unwrap();
+        assert_eq!(tail.read_lines(1024).unwrap(), b"a=1\n");
+        assert_eq!(tail.checkpoint().offset, 4);
+        writer.write_all(b"2\nc=3\n").unwrap();
+        assert_eq!(tail.read_lines(1024).unwrap(), b"b=2\nc=3\n");
+        assert!(tail.reopen_if_rotated().unwrap().is_none());
+
+        // resume from the checkpoint
+        let checkpoint = Checkpoint {
+            file_id: tail.file_id,
+            offset: 4,
+        };
+        let mut resumed = Tail::open(&path, Some(checkpoint)).unwrap();
+        assert_eq!(resumed.read_lines(1024).unwrap(), b"b=2\nc=3\n");
+        drop(resumed);
+
+        // truncate the file
+        writer.set_len(0).unwrap();
+        assert_eq!(tail.reopen_if_rotated().unwrap(), Some(vec![]));
+        assert_eq!(tail.checkpoint().offset, 0);
+
+        drop(writer);
+        drop(tail);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tail_read_limit() {
+        let path =
+            std::env::temp_dir().join(format!("scopeql-tail-limit-{}.log", std::process::id()));
+        std::fs::write(&path, b"a=1\nb=2\nc=3\nmessage=long\nd=").unwrap();
+
+        let mut tail = Tail::open(&path, None).unwrap();
+        assert_eq!(tail.read_lines(6).unwrap(), b"a=1\n");
+        assert_eq!(tail.read_lines(6).unwrap(), b"b=2\nc=3\n");
+        // a line longer than the limit is read as a whole
+        assert_eq!(tail.read_lines(6).unwrap(), b"message=long\n");
+        assert_eq!(tail.read_lines(6).unwrap(), b"");
+        assert_eq!(tail.checkpoint().offset, 25);
+
+        drop(tail);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Windows refuses to rename or delete open files, and rotation is not detected there.
+    #[cfg(unix)]
+    #[test]
+    fn test_tail_rotate() {
+        let path =
+            std::env::temp_dir().join(format!("scopeql-tail-recreate-{}.log", std::process::id()));
+        std::fs::write(&path, b"a=1\n").unwrap();
+
+        let mut tail = Tail::open(&path, None).unwrap();
+        assert_eq!(tail.read_lines(1024).unwrap(), b"a=1\n");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(tail.reopen_if_rotated().unwrap().is_none());
+        std::fs::write(&path, b"b=2\n").unwrap();
+        assert_eq!(tail.reopen_if_rotated().unwrap(), Some(vec![]));
+        assert_eq!(tail.read_lines(1024).unwrap(), b"b=2\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tail_rotate_drains_old_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("scopeql-tail-rotate-{}.log", std::process::id()));
+        let rotated = dir.join(format!("scopeql-tail-rotate-{}.log.1", std::process::id()));
+        let mut writer = File::create(&path).unwrap();
+        writer.write_all(b"a=1\n").unwrap();
+
+        let mut tail = Tail::open(&path, None).unwrap();
+        assert_eq!(tail.read_lines(1024).unwrap(), b"a=1\n");
+
+        // lines written right before the rotation must not be lost
+        writer.write_all(b"b=2\nc=").unwrap();
+        std::fs::rename(&path, &rotated).unwrap();
+        writer.write_all(b"3").unwrap();
+        std::fs::write(&path, b"d=4\n").unwrap();
+
+        // the rotated file is read to the end before it is reopened
+        assert!(tail.reopen_if_rotated().unwrap().is_none());
+        assert_eq!(tail.read_lines(1024).unwrap(), b"b=2\n");
+        assert_eq!(tail.reopen_if_rotated().unwrap(), Some(b"c=3".to_vec()));
+        assert_eq!(tail.checkpoint().offset, 0);
+        assert_eq!(tail.read_lines(1024).unwrap(), b"d=4\n");
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_append_rows_flushes_full_batch() {
+        #[derive(Parser)]
+        struct Args {
+            #[clap(flatten)]
+            format_options: FormatOptions,
+            #[clap(flatten)]
+            ingest_options: IngestOptions,
+        }
+        let args = Args::parse_from(["scopeql", "--batch-rows", "2"]);
+
+        let lines = b"a=1\nb=2\nc=3\nd=4\ne=5\n".to_vec();
+        let rows = read_rows(lines, DataFormat::Logfmt, &args.format_options).unwrap();
+        let checkpoint = Checkpoint {
+            file_id: None,
+            offset: 42,
+        };
+        let mut batch = Batch::default();
+        let mut flushed = vec![];
+        let mut flush = async |batch: &mut Batch, checkpoint: &Checkpoint| {
+            flushed.push((batch.rows, checkpoint.offset));
+            *batch = Batch::default();
+            Ok(())
+        };
+        append_rows(
+            &mut batch,
+            rows,
+            Path::new("a.log"),
+            None,
+            &args.ingest_options,
+            &checkpoint,
+            &mut flush,
+        )
+        .await
+        .unwrap();
+
+        // full batches are flushed with the offset before the rows
+        assert_eq!(flushed, vec![(2, 42), (2, 42)]);
+        assert_eq!(batch.rows, 1);
+    }
+}
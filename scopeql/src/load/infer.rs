@@ -0,0 +1,441 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use exn::Result;
+use exn::bail;
+use indicatif::ProgressBar;
+use scopeql_parser::TokenKind;
+use serde_json::Value;
+
+use crate::Error;
+use crate::client::DataType;
+use crate::load::FormatOptions;
+use crate::load::Row;
+use crate::load::csv::is_canonical_number;
+use crate::load::display_name;
+use crate::load::expand_files;
+use crate::load::open_rows;
+use crate::load::preview::quote_string;
+use crate::load::resolve_format;
+use crate::tokenizer::run_tokenizer;
+
+/// Maximum number of distinct sample values reported for a column.
+const MAX_SAMPLES: usize = 3;
+/// Maximum length of a sample value in the report; longer values are truncated.
+const MAX_SAMPLE_LEN: usize = 32;
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Inference Options")]
+pub struct InferOptions {
+    /// Maximum number of rows scanned across all files.
+    #[clap(long, value_name = "ROWS", default_value = "10000")]
+    pub sample_rows: usize,
+    /// Also print a `CREATE TABLE` statement of the inferred columns for the given table, and a
+    /// transform that loads the rows into it.
+    #[clap(long, value_name = "NAME")]
+    pub table: Option<String>,
+}
+
+/// Scans the files, and prints the inferred columns with their types, null ratios and sample
+/// values.
+pub fn infer(files: Vec<PathBuf>, format_options: FormatOptions, options: InferOptions) {
+    match infer_schema(files, &format_options, options.sample_rows) {
+        Ok(schema) => {
+            println!("{}", schema.report());
+            if let Some(table) = options.table {
+                println!();
+                println!("{}", schema.create_table(&table));
+                println!();
+                println!("{}", schema.transform(&table));
+            }
+        }
+        Err(err) => {
+            log::error!("failed to infer schema: {err:?}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn infer_schema(
+    files: Vec<PathBuf>,
+    format_options: &FormatOptions,
+    sample_rows: usize,
+) -> Result<Schema, Error> {
+    let mut schema = Schema::default();
+    for file in expand_files(files)? {
+        if schema.num_rows >= sample_rows {
+            break;
+        }
+        let format = resolve_format(&file, format_options)?;
+        let rows = open_rows(&file, format, format_options.clone(), ProgressBar::hidden())?;
+        for row in rows {
+            if schema.num_rows >= sample_rows {
+                break;
+            }
+            match row? {
+                Row::Valid(value) => schema.add_row(value),
+                Row::Rejected(row) => {
+                    log::warn!("{}: skipped a malformed row ({row})", display_name(&file))
+                }
+            }
+        }
+    }
+    if schema.num_rows == 0 {
+        bail!(Error::new("no rows to infer the schema from".to_string()));
+    }
+    Ok(schema)
+}
+
+/// The columns inferred from the rows scanned, in the order they first appear.
+#[derive(Debug, Default)]
+struct Schema {
+    num_rows: usize,
+    columns: Vec<Column>,
+    index: HashMap<String, usize>,
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    /// The type of the non-null values, or `None` if all values are null.
+    data_type: Option<DataType>,
+    num_values: usize,
+    samples: Vec<String>,
+}
+
+impl Schema {
+    fn add_row(&mut self, row: Value) {
+        self.num_rows += 1;
+        match row {
+            Value::Object(object) => {
+                for (name, value) in object {
+                    self.add_value(name, value);
+                }
+            }
+            // a non-object row is loaded as a whole
+            value => self.add_value("$0".to_string(), value),
+        }
+    }
+
+    fn add_value(&mut self, name: String, value: Value) {
+        let index = match self.index.get(&name) {
+            Some(index) => *index,
+            None => {
+                self.index.insert(name.clone(), self.columns.len());
+                self.columns.push(Column {
+                    name,
+                    data_type: None,
+                    num_values: 0,
+                    samples: vec![],
+                });
+                self.columns.len() - 1
+            }
+        };
+
+        let column = &mut self.columns[index];
+        let Some(data_type) = infer_type(&value) else {
+            return;
+        };
+        column.num_values += 1;
+        column.data_type = Some(match column.data_type {
+            None => data_type,
+            Some(current) => merge_types(current, data_type),
+        });
+        if column.samples.len() < MAX_SAMPLES {
+            let sample = match value {
+                Value::String(s) if !s.is_empty() => s,
+                value => value.to_string(),
+            };
+            let sample = truncate(sample);
+            if !column.samples.contains(&sample) {
+                column.samples.push(sample);
+            }
+        }
+    }
+
+    /// Formats the inferred columns as a table.
+    fn report(&self) -> String {
+        // the same style as result sets
+        const TABLE_STYLE_PRESET: &str = "||--+-++|    ++++++";
+        let mut table = comfy_table::Table::new();
+        table.load_preset(TABLE_STYLE_PRESET);
+        table.set_header(["column", "type", "nulls", "samples"]);
+        for column in &self.columns {
+            let num_nulls = self.num_rows - column.num_values;
+            table.add_row([
+                column.name.clone(),
+                column.data_type().to_string(),
+                format!("{:.1}%", num_nulls as f64 * 100.0 / self.num_rows as f64),
+                column.samples.join(", "),
+            ]);
+        }
+        let num_rows = match self.num_rows {
+            1 => "1 row".to_string(),
+            n => format!("{n} rows"),
+        };
+        format!(
+            "{table}\n({} columns inferred from {num_rows})",
+            self.columns.len()
+        )
+    }
+
+    fn create_table(&self, table: &str) -> String {
+        let columns = self
+            .column_names()
+            .iter()
+            .zip(&self.columns)
+            .map(|(name, column)| format!("    {name} {}", column.data_type()))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let table = quote_table_name(table);
+        format!("CREATE TABLE {table} (\n{columns}\n);")
+    }
+
+    fn transform(&self, table: &str) -> String {
+        let columns = self
+            .column_names()
+            .iter()
+            .zip(&self.columns)
+            .map(|(name, column)| {
+                let value = match column.name.as_str() {
+                    "$0" => "$0".to_string(),
+                    key => format!("$0[{}]", quote_string(key)),
+                };
+                match column.data_type() {
+                    DataType::Any => format!("    {value} AS {name}"),
+                    data_type => format!("    {value}::{data_type} AS {name}"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let table = quote_table_name(table);
+        format!("SELECT\n{columns}\nINSERT INTO {table}")
+    }
+
+    /// Makes unique table column names of the columns.
+    fn column_names(&self) -> Vec<String> {
+        let mut used = HashSet::new();
+        self.columns
+            .iter()
+            .map(|column| {
+                let base = normalize_name(&column.name);
+                let mut name = base.clone();
+                let mut n = 1;
+                while !used.insert(name.clone()) {
+                    n += 1;
+                    name = format!("{base}_{n}");
+                }
+                quote_ident(name)
+            })
+            .collect()
+    }
+}
+
+impl Column {
+    /// The type of the column in a table; columns of only null values can hold any value.
+    fn data_type(&self) -> DataType {
+        self.data_type.unwrap_or(DataType::Any)
+    }
+}
+
+/// Infers the type of a value, or `None` for null. Strings of numbers, booleans and RFC 3339
+/// timestamps are inferred as such, so that they are cast in the transform; strings of numbers
+/// with a `+` sign or leading zeros, e.g., `007`, stay strings.
+fn infer_type(value: &Value) -> Option<DataType> {
+    let data_type = match value {
+        Value::Null => return None,
+        Value::Bool(_) => DataType::Boolean,
+        Value::Number(n) if n.is_i64() => DataType::Int,
+        Value::Number(n) if n.is_u64() => DataType::UInt,
+        Value::Number(_) => DataType::Float,
+        Value::String(s) => {
+            let is_number = is_canonical_number(s);
+            if is_number && s.parse::<i64>().is_ok() {
+                DataType::Int
+            } else if is_number && s.parse::<f64>().is_ok_and(f64::is_finite) {
+                DataType::Float
+            } else if s == "true" || s == "false" {
+                DataType::Boolean
+            } else if s.parse::<jiff::Timestamp>().is_ok() {
+                DataType::Timestamp
+            } else {
+                DataType::String
+            }
+        }
+        Value::Array(_) => DataType::Array,
+        Value::Object(_) => DataType::Object,
+    };
+    Some(data_type)
+}
+
+/// Merges the types of values of the same column.
+fn merge_types(a: DataType, b: DataType) -> DataType {
+    use DataType::*;
+
+    match (a, b) {
+        (a, b) if a == b => a,
+        // unsigned integers are inferred only beyond the range of int, so that no integer type
+        // holds both without loss
+        (Int, UInt) | (UInt, Int) => Any,
+        (Int | UInt | Float, Int | UInt | Float) => Float,
+        // a string column may contain values that look like numbers, booleans or timestamps
+        (String, Int | UInt | Float | Boolean | Timestamp)
+        | (Int | UInt | Float | Boolean | Timestamp, String) => String,
+        _ => Any,
+    }
+}
+
+fn truncate(mut s: String) -> String {
+    if let Some((index, _)) = s.char_indices().nth(MAX_SAMPLE_LEN) {
+        s.truncate(index);
+        s.push('…');
+    }
+    s
+}
+
+/// Normalizes the name of a field into a lower snake case identifier.
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            normalized.push(c.to_ascii_lowercase());
+        } else if !normalized.ends_with('_') {
+            normalized.push('_');
+        }
+    }
+    let normalized = normalized.trim_matches('_');
+    match normalized.chars().next() {
+        None => "col".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{normalized}"),
+        Some(_) => normalized.to_string(),
+    }
+}
+
+/// Quotes the identifier if it is a keyword or not a plain identifier.
+fn quote_ident(name: String) -> String {
+    match run_tokenizer(&name).as_deref() {
+        Ok([token, eoi]) if token.kind == TokenKind::Ident && eoi.kind == TokenKind::EOI => name,
+        _ => format!("`{}`", name.replace('`', "``")),
+    }
+}
+
+/// Quotes each part of the possibly qualified table name if needed; parts already quoted with
+/// backquotes are kept as is.
+fn quote_table_name(table: &str) -> String {
+    table
+        .split('.')
+        .map(|part| {
+            if part.len() >= 2 && part.starts_with('`') && part.ends_with('`') {
+                part.to_string()
+            } else {
+                quote_ident(part.to_string())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_infer_schema() {
+        let mut schema = Schema::default();
+        schema.add_row(
+            json!({"Id": 1, "ts": "2026-01-01T00:00:00Z", "user name": "a", "tags": ["x"]}),
+        );
+        schema.add_row(json!({"Id": 2.5, "ts": null, "user name": "b", "extra": {"k": 1}}));
+        schema
+            .add_row(json!({"Id": "3", "ts": "2026-01-02T00:00:00Z", "user name": "a", "from": 1}));
+
+        assert_eq!(
+            schema.create_table("t"),
+            "CREATE TABLE t (\n    id float,\n    tags array,\n    ts timestamp,\n    user_name string,\n    extra object,\n    `from` int\n);"
+        );
+        assert_eq!(
+            schema.transform("t"),
+            "SELECT\n    $0['Id']::float AS id,\n    $0['tags']::array AS tags,\n    $0['ts']::timestamp AS ts,\n    $0['user name']::string AS user_name,\n    $0['extra']::object AS extra,\n    $0['from']::int AS `from`\nINSERT INTO t"
+        );
+
+        let report = schema.report();
+        assert!(
+            report.contains(
+                "| ts        | timestamp | 33.3% | 2026-01-01T00:00:00Z, 2026-01-02T00:00:00Z |"
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains("| user name | string    | 0.0%  | a, b"),
+            "{report}"
+        );
+        assert!(
+            report.ends_with("(6 columns inferred from 3 rows)"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn test_infer_type() {
+        assert_eq!(infer_type(&json!("42")), Some(DataType::Int));
+        assert_eq!(infer_type(&json!("-0.5")), Some(DataType::Float));
+        for s in ["007", "+1", "00.5", "+1.5"] {
+            assert_eq!(infer_type(&json!(s)), Some(DataType::String), "{s}");
+        }
+        assert_eq!(infer_type(&json!(7)), Some(DataType::Int));
+        assert_eq!(infer_type(&json!(null)), None);
+    }
+
+    #[test]
+    fn test_merge_types() {
+        assert_eq!(merge_types(DataType::Int, DataType::UInt), DataType::Any);
+        assert_eq!(
+            merge_types(DataType::UInt, DataType::Float),
+            DataType::Float
+        );
+        assert_eq!(
+            merge_types(DataType::Int, DataType::String),
+            DataType::String
+        );
+        assert_eq!(
+            merge_types(DataType::String, DataType::UInt),
+            DataType::String
+        );
+        assert_eq!(
+            merge_types(DataType::Array, DataType::Object),
+            DataType::Any
+        );
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("9 Lives!"), "_9_lives");
+        assert_eq!(normalize_name("--"), "col");
+    }
+
+    #[test]
+    fn test_quote_table_name() {
+        assert_eq!(quote_table_name("events"), "events");
+        assert_eq!(quote_table_name("logs.from"), "logs.`from`");
+        assert_eq!(quote_table_name("my table"), "`my table`");
+        assert_eq!(quote_table_name("a`b"), "`a``b`");
+        assert_eq!(quote_table_name("`my table`"), "`my table`");
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+use std::str::FromStr;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use serde_json::Value;
+
+use crate::Error;
+use crate::load::Row;
+use crate::load::RowReader;
+use crate::load::reject::RejectedRow;
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "JSON Options")]
+pub struct JsonOptions {
+    /// Load the elements of the array at the given path of each JSON value, e.g., `$.records`;
+    /// a non-array value at the path is loaded as a single row.
+    ///
+    /// The path consists of `.key`, `['key']` and `[index]` segments after the root `$`.
+    #[clap(long, value_name = "PATH")]
+    pub json_path: Option<JsonPath>,
+}
+
+/// The layout of JSON values in a data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLayout {
+    /// Detects a top-level array, and falls back to a stream of values.
+    Auto,
+    /// A single top-level array of values.
+    Array,
+    /// One value per line.
+    Lines,
+    /// A stream of values separated by optional whitespaces.
+    Stream,
+}
+
+/// A simple JSONPath that selects a single value, e.g., `$.data.records` or `$['data'][0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<JsonPathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let Some(mut rest) = s.trim().strip_prefix('$') else {
+            return Err(format!("JSON path must start with `$`: {s}"));
+        };
+
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end == 0 {
+                    return Err(format!("empty key in JSON path: {s}"));
+                }
+                segments.push(JsonPathSegment::Key(r[..end].to_string()));
+                rest = &r[end..];
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let Some(end) = r.find(']') else {
+                    return Err(format!("unclosed `[` in JSON path: {s}"));
+                };
+                let segment = r[..end].trim();
+                let key = segment
+                    .strip_prefix('\'')
+                    .and_then(|k| k.strip_suffix('\''))
+                    .or_else(|| segment.strip_prefix('"').and_then(|k| k.strip_suffix('"')));
+                match key {
+                    Some(key) => segments.push(JsonPathSegment::Key(key.to_string())),
+                    None => match segment.parse() {
+                        Ok(index) => segments.push(JsonPathSegment::Index(index)),
+                        Err(_) => {
+                            return Err(format!("invalid segment `[{segment}]` in JSON path: {s}"));
+                        }
+                    },
+                }
+                rest = &r[end + 1..];
+            } else {
+                return Err(format!("expected `.` or `[` in JSON path: {s}"));
+            }
+        }
+        Ok(JsonPath { segments })
+    }
+}
+
+impl JsonPath {
+    /// Takes the value at the path out of the given value.
+    fn select(&self, mut value: Value) -> Option<Value> {
+        for segment in &self.segments {
+            value = match (segment, value) {
+                (JsonPathSegment::Key(key), Value::Object(mut object)) => object.remove(key)?,
+                (JsonPathSegment::Index(index), Value::Array(mut array))
+                    if *index < array.len() =>
+                {
+                    array.swap_remove(*index)
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Reads JSON values in the given layout as rows.
+///
+/// Malformed lines and array elements are rejected, and reading continues with the next one;
+/// other malformed data fails the read since the position of the next value is lost.
+pub fn read_json_rows<R: BufRead + 'static>(
+    mut reader: R,
+    layout: JsonLayout,
+    options: JsonOptions,
+) -> Result<RowReader, Error> {
+    let layout = match layout {
+        JsonLayout::Auto => {
+            let first = first_non_whitespace(&mut reader)
+                .or_raise(|| Error::new("failed to read json data".to_string()))?;
+            if first == Some(b'[') && options.json_path.is_none() {
+                JsonLayout::Array
+            } else {
+                JsonLayout::Stream
+            }
+        }
+        layout => layout,
+    };
+
+    let values: RowReader = match layout {
+        JsonLayout::Auto | JsonLayout::Stream => Box::new(
+            serde_json::Deserializer::from_reader(reader)
+                .into_iter::<Value>()
+                .map(|row| {
+                    row.map(Row::Valid)
+                        .or_raise(|| Error::new("failed to read json value".to_string()))
+                }),
+        ),
+        JsonLayout::Lines => Box::new(read_json_lines(reader)),
+        JsonLayout::Array => Box::new(JsonArrayReader::new(reader)),
+    };
+
+    let Some(path) = options.json_path else {
+        return Ok(values);
+    };
+    Ok(Box::new(values.flat_map(move |row| {
+        let rows: Vec<Result<Row, Error>> = match row {
+            Ok(Row::Valid(value)) => match path.select(value) {
+                Some(Value::Array(rows)) => {
+                    rows.into_iter().map(|row| Ok(Row::Valid(row))).collect()
+                }
+                Some(row) => vec![Ok(Row::Valid(row))],
+                None => vec![Ok(Row::Rejected(RejectedRow {
+                    line: None,
+                    reason: "JSON path not found in value".to_string(),
+                    raw: None,
+                }))],
+            },
+            row => vec![row],
+        };
+        rows
+    })))
+}
+
+/// Reads one JSON value per line; blank lines are skipped, and malformed lines are rejected.
+fn read_json_lines<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Row, Error>> {
+    reader.lines().enumerate().filter_map(|(n, line)| {
+        let line_number = n as u64 + 1;
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => return None,
+            Ok(line) => line,
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                return Some(Ok(Row::Rejected(RejectedRow {
+                    line: Some(line_number),
+                    reason: "line is not valid UTF-8".to_string(),
+                    raw: None,
+                })));
+            }
+            Err(err) => {
+                let make_error =
+                    || Error::new(format!("failed to read json value at line {line_number}"));
+                return Some(Err(err).or_raise(make_error));
+            }
+        };
+        Some(Ok(match serde_json::from_str(&line) {
+            Ok(value) => Row::Valid(value),
+            Err(err) => Row::Rejected(RejectedRow {
+                line: Some(line_number),
+                reason: format!("failed to parse json value: {err}"),
+                raw: Some(line),
+            }),
+        }))
+    })
+}
+
+/// Skips leading whitespaces, and returns the next byte without consuming it.
+fn first_non_whitespace<R: BufRead>(reader: &mut R) -> std::io::Result<Option<u8>> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(pos) => {
+                let first = buf[pos];
+                reader.consume(pos);
+                return Ok(Some(first));
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Reads the elements of a top-level JSON array one by one, without reading the whole array
+/// into memory.
+struct JsonArrayReader<R> {
+    reader: R,
+    state: JsonArrayState,
+    element: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonArrayState {
+    /// Expects the opening `[`.
+    Start,
+    /// Expects the first element or the closing `]`.
+    First,
+    /// Expects a `,` or the closing `]`.
+    Next,
+    Done,
+}
+
+impl<R: BufRead> JsonArrayReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: JsonArrayState::Start,
+            element: vec![],
+        }
+    }
+
+    fn read_next(&mut self) -> Result<Option<Row>, Error> {
+        let make_error = || Error::new("failed to read json array".to_string());
+
+        loop {
+            let first = first_non_whitespace(&mut self.reader).or_raise(make_error)?;
+            match (self.state, first) {
+                (JsonArrayState::Done, None) => return Ok(None),
+                (JsonArrayState::Done, Some(_)) => {
+                    bail!(Error::new(
+                        "trailing characters after json array".to_string()
+                    ))
+                }
+                (JsonArrayState::Start, Some(b'[')) => {
+                    self.reader.consume(1);
+                    self.state = JsonArrayState::First;
+                }
+                (JsonArrayState::Start, _) => {
+                    bail!(Error::new("expected a json array".to_string()))
+                }
+                (JsonArrayState::First | JsonArrayState::Next, Some(b']')) => {
+                    self.reader.consume(1);
+                    self.state = JsonArrayState::Done;
+                }
+                (JsonArrayState::Next, Some(b',')) => {
+                    self.reader.consume(1);
+                    self.state = JsonArrayState::First;
+                    // an element must follow the comma
+                    match first_non_whitespace(&mut self.reader).or_raise(make_error)? {
+                        Some(b']') | None => bail!(Error::new("expected a json value".to_string())),
+                        Some(_) => return self.read_element().map(Some),
+                    }
+                }
+                (JsonArrayState::First, Some(_)) => return self.read_element().map(Some),
+                (_, _) => bail!(Error::new("expected `,` or `]` in json array".to_string())),
+            }
+        }
+    }
+
+    /// Reads the bytes of the next element up to the `,` or `]` at the top level, and parses
+    /// them as a JSON value; an element that cannot be parsed is rejected.
+    fn read_element(&mut self) -> Result<Row, Error> {
+        let make_error = || Error::new("failed to read json array element".to_string());
+
+        self.element.clear();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        'read: loop {
+            let buf = self.reader.fill_buf().or_raise(make_error)?;
+            if buf.is_empty() {
+                bail!(Error::new("unexpected end of json array".to_string()));
+            }
+            for (i, &b) in buf.iter().enumerate() {
+                if in_string {
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                match b {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b',' | b']' if depth == 0 => {
+                        self.element.extend_from_slice(&buf[..i]);
+                        self.reader.consume(i);
+                        break 'read;
+                    }
+                    _ => {}
+                }
+            }
+            let len = buf.len();
+            self.element.extend_from_slice(buf);
+            self.reader.consume(len);
+        }
+
+        self.state = JsonArrayState::Next;
+        Ok(match serde_json::from_slice(&self.element) {
+            Ok(value) => Row::Valid(value),
+            Err(err) => Row::Rejected(RejectedRow {
+                line: None,
+                reason: format!("failed to parse json array element: {err}"),
+                raw: Some(String::from_utf8_lossy(self.element.trim_ascii()).into_owned()),
+            }),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for JsonArrayReader<R> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_next() {
+            Ok(value) => value.map(Ok),
+            Err(err) => {
+                // stop at the first error since the position in the array is lost
+                self.state = JsonArrayState::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn read(data: &'static str, layout: JsonLayout, json_path: Option<&str>) -> Vec<Value> {
+        let options = JsonOptions {
+            json_path: json_path.map(|path| path.parse().unwrap()),
+        };
+        read_json_rows(data.as_bytes(), layout, options)
+            .unwrap()
+            .map(|row| match row.unwrap() {
+                Row::Valid(value) => value,
+                Row::Rejected(row) => panic!("unexpected rejected row: {row}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_json_rows() {
+        let array = r#" [ {"a": 1, "b": "x,]}"}, [1, [2]] , 3 ] "#;
+        let expected = vec![json!({"a": 1, "b": "x,]}"}), json!([1, [2]]), json!(3)];
+        assert_eq!(read(array, JsonLayout::Auto, None), expected);
+        assert_eq!(read(array, JsonLayout::Array, None), expected);
+        assert_eq!(
+            read(array, JsonLayout::Stream, None),
+            vec![json!([{"a": 1, "b": "x,]}"}, [1, [2]], 3])]
+        );
+        assert_eq!(read("[]", JsonLayout::Array, None), Vec::<Value>::new());
+
+        let lines = "{\"a\": 1}\n\n{\"a\": 2}\n";
+        assert_eq!(
+            read(lines, JsonLayout::Auto, None),
+            vec![json!({"a": 1}), json!({"a": 2})]
+        );
+        assert_eq!(
+            read(lines, JsonLayout::Lines, None),
+            vec![json!({"a": 1}), json!({"a": 2})]
+        );
+
+        let dump = r#"{"data": {"records": [{"a": 1}, {"a": 2}]}} {"data": {"records": {"a": 3}}}"#;
+        assert_eq!(
+            read(dump, JsonLayout::Auto, Some("$.data['records']")),
+            vec![json!({"a": 1}), json!({"a": 2}), json!({"a": 3})]
+        );
+
+        let errors = ["[1, 2", "[1,]", "[1] 2", "{}"];
+        for data in errors {
+            let rows = read_json_rows(
+                data.as_bytes(),
+                JsonLayout::Array,
+                JsonOptions { json_path: None },
+            );
+            assert!(rows.unwrap().any(|row| row.is_err()), "{data}");
+        }
+    }
+
+    #[test]
+    fn test_reject_json_rows() {
+        let lines = "{\"a\": 1}\n{\"a\": \n[2]\n";
+        let rows = read_json_rows(
+            lines.as_bytes(),
+            JsonLayout::Lines,
+            JsonOptions { json_path: None },
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], Row::Valid(json!({"a": 1})));
+        assert!(matches!(
+            &rows[1],
+            Row::Rejected(RejectedRow { line: Some(2), raw: Some(raw), .. }) if raw == "{\"a\": "
+        ));
+        assert_eq!(rows[2], Row::Valid(json!([2])));
+
+        let array = "[1, {x}, 3 4, 5]";
+        let rows = read_json_rows(
+            array.as_bytes(),
+            JsonLayout::Array,
+            JsonOptions { json_path: None },
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(
+            &rows[1],
+            Row::Rejected(RejectedRow { line: None, raw: Some(raw), .. }) if raw == "{x}"
+        ));
+        assert!(matches!(&rows[2], Row::Rejected(_)));
+        assert_eq!(rows[3], Row::Valid(json!(5)));
+    }
+
+    #[test]
+    fn test_parse_json_path() {
+        let path = JsonPath::from_str("$.a['b.c'][2]").unwrap();
+        assert_eq!(
+            path.segments,
+            vec![
+                JsonPathSegment::Key("a".to_string()),
+                JsonPathSegment::Key("b.c".to_string()),
+                JsonPathSegment::Index(2),
+            ]
+        );
+        assert_eq!(JsonPath::from_str("$").unwrap().segments, vec![]);
+        assert!(JsonPath::from_str("a.b").is_err());
+        assert!(JsonPath::from_str("$.").is_err());
+        assert!(JsonPath::from_str("$[x]").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+use std::sync::LazyLock;
+
+use exn::Result;
+use exn::ResultExt;
+use regex::Captures;
+use regex::Regex;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::Error;
+use crate::load::Row;
+use crate::load::reject::RejectedRow;
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Log Options")]
+pub struct LogOptions {
+    /// Parse each line with the given regular expression, and load its named capture groups as
+    /// fields, e.g., `^(?P<level>\w+) (?P<message>.*)$`; implies `--format regex`.
+    ///
+    /// Groups that do not participate in the match are omitted.
+    #[clap(long, value_name = "PATTERN")]
+    pub regex: Option<Regex>,
+}
+
+/// A format of line-oriented log files.
+#[derive(Debug, Clone)]
+pub enum LogFormat {
+    Logfmt,
+    Syslog,
+    Clf,
+    Regex(Regex),
+}
+
+/// Parses each non-empty line of a log file as a row.
+///
+/// Lines that cannot be parsed are rejected with their line numbers, and reading continues
+/// with the next line.
+pub(crate) struct LogReader<R> {
+    reader: R,
+    format: LogFormat,
+    line: Vec<u8>,
+    line_number: usize,
+}
+
+impl<R: BufRead> LogReader<R> {
+    pub fn new(reader: R, format: LogFormat) -> Self {
+        Self {
+            reader,
+            format,
+            line: vec![],
+            line_number: 0,
+        }
+    }
+
+    fn parse(&self, line: &str) -> Option<Value> {
+        match &self.format {
+            LogFormat::Logfmt => parse_logfmt(line),
+            LogFormat::Syslog => parse_syslog(line),
+            LogFormat::Clf => parse_clf(line),
+            LogFormat::Regex(regex) => parse_regex(regex, line),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            self.line_number += 1;
+            let line_number = self.line_number;
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => {
+                    let err = Err(err)
+                        .or_raise(|| Error::new(format!("failed to read log line {line_number}")));
+                    return Some(err);
+                }
+            }
+
+            let line = String::from_utf8_lossy(&self.line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(Ok(match self.parse(line) {
+                Some(row) => Row::Valid(row),
+                None => Row::Rejected(RejectedRow {
+                    line: Some(line_number as u64),
+                    reason: "failed to parse log line".to_string(),
+                    raw: Some(line.to_string()),
+                }),
+            }));
+        }
+    }
+}
+
+/// Parses a logfmt line, e.g., `level=info msg="hello world" verbose`.
+///
+/// Values are loaded as strings, and keys without values as `true`.
+fn parse_logfmt(line: &str) -> Option<Value> {
+    let mut row = Map::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && !c.is_whitespace()) {
+            key.push(c);
+        }
+        if key.is_empty() {
+            return None;
+        }
+        if chars.next_if_eq(&'=').is_none() {
+            row.insert(key, Value::Bool(true));
+            continue;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        c => value.push(c),
+                    },
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+        row.insert(key, Value::String(value));
+    }
+    (!row.is_empty()).then_some(Value::Object(row))
+}
+
+/// Parses an RFC 5424 or RFC 3164 (BSD) syslog line; the priority of BSD syslog lines is
+/// optional, as it is omitted in log files written by most syslog daemons.
+fn parse_syslog(line: &str) -> Option<Value> {
+    static PRIORITY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^<(\d{1,3})>").unwrap());
+    static RFC5424: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?P<version>[1-9]\d{0,2}) (?P<timestamp>\S+) (?P<hostname>\S+) (?P<appname>\S+) (?P<procid>\S+) (?P<msgid>\S+) ")
+            .unwrap()
+    });
+    static RFC3164: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?P<timestamp>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (?P<hostname>\S+) (?:(?P<appname>[^\s\[:]+)(?:\[(?P<procid>[^\]]*)\])?: ?)?(?P<message>.*)$")
+            .unwrap()
+    });
+
+    let mut row = Map::new();
+    let mut rest = line;
+    if let Some(caps) = PRIORITY.captures(line) {
+        let priority: u64 = caps[1].parse().ok().filter(|p| *p < 192)?;
+        row.insert("facility".to_string(), Value::from(priority / 8));
+        row.insert("severity".to_string(), Value::from(priority % 8));
+        rest = &line[caps[0].len()..];
+
+        if let Some(caps) = RFC5424.captures(rest) {
+            insert_captures(&mut row, &RFC5424, &caps, |v| v != "-");
+            if let Some(version) = row.get_mut("version") {
+                *version = Value::from(version.as_str()?.parse::<u64>().ok()?);
+            }
+            let (structured_data, message) = parse_structured_data(&rest[caps[0].len()..])?;
+            if let Some(structured_data) = structured_data {
+                row.insert("structured_data".to_string(), structured_data);
+            }
+            if let Some(message) = message {
+                let message = message.strip_prefix('\u{FEFF}').unwrap_or(message);
+                row.insert("message".to_string(), Value::from(message));
+            }
+            return Some(Value::Object(row));
+        }
+    }
+
+    let caps = RFC3164.captures(rest)?;
+    insert_captures(&mut row, &RFC3164, &caps, |_| true);
+    Some(Value::Object(row))
+}
+
+/// Parses the RFC 5424 structured data, e.g., `[id@1 key="value"]`, as an object keyed by the
+/// element IDs, and returns it with the remaining message.
+fn parse_structured_data(s: &str) -> Option<(Option<Value>, Option<&str>)> {
+    if let Some(message) = s.strip_prefix('-') {
+        let message = message.strip_prefix(' ').filter(|m| !m.is_empty());
+        return Some((None, message));
+    }
+
+    let mut elements = Map::new();
+    let mut rest = s;
+    while let Some(r) = rest.strip_prefix('[') {
+        let end = r.find([' ', ']'])?;
+        let id = &r[..end];
+        let mut params = Map::new();
+        rest = &r[end..];
+        loop {
+            if let Some(r) = rest.strip_prefix(']') {
+                rest = r;
+                break;
+            }
+            let r = rest.strip_prefix(' ')?;
+            let eq = r.find('=')?;
+            let name = &r[..eq];
+            let mut chars = r[eq + 1..].strip_prefix('"')?.char_indices();
+            let mut value = String::new();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i,
+                    (_, '\\') => match chars.next()?.1 {
+                        c @ ('"' | '\\' | ']') => value.push(c),
+                        c => value.extend(['\\', c]),
+                    },
+                    (_, c) => value.push(c),
+                }
+            };
+            params.insert(name.to_string(), Value::String(value));
+            rest = &r[eq + 1..][end + 2..];
+        }
+        elements.insert(id.to_string(), Value::Object(params));
+    }
+
+    if elements.is_empty() {
+        return None;
+    }
+    let message = rest.strip_prefix(' ').filter(|m| !m.is_empty());
+    Some((Some(Value::Object(elements)), message))
+}
+
+/// Parses an Apache/Nginx common or combined log format line, e.g.,
+/// `127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /a.gif HTTP/1.0" 200 2326 "-" "curl"`.
+///
+/// Fields of `-` are omitted, and the timestamp is converted to RFC 3339.
+fn parse_clf(line: &str) -> Option<Value> {
+    static CLF: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"^(?P<remote_host>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<timestamp>[^\]]+)\] "(?P<request>(?:[^"\\]|\\.)*)" (?P<status>\d{3}) (?P<bytes>\d+|-)(?: "(?P<referer>(?:[^"\\]|\\.)*)" "(?P<user_agent>(?:[^"\\]|\\.)*)")?"#)
+            .unwrap()
+    });
+
+    let caps = CLF.captures(line)?;
+    let mut row = Map::new();
+    insert_captures(&mut row, &CLF, &caps, |v| v != "-");
+
+    if let Some(timestamp) = row.get_mut("timestamp") {
+        let parsed = jiff::Timestamp::strptime("%d/%b/%Y:%H:%M:%S %z", timestamp.as_str()?);
+        if let Ok(parsed) = parsed {
+            *timestamp = Value::String(parsed.to_string());
+        }
+    }
+    for field in ["status", "bytes"] {
+        if let Some(value) = row.get_mut(field) {
+            *value = Value::from(value.as_str()?.parse::<u64>().ok()?);
+        }
+    }
+    if let Some(Value::String(request)) = row.get("request") {
+        let parts = request.splitn(3, ' ').collect::<Vec<_>>();
+        if let [method, path, protocol] = parts[..] {
+            let (method, path, protocol) = (method.into(), path.into(), protocol.into());
+            row.insert("method".to_string(), Value::String(method));
+            row.insert("path".to_string(), Value::String(path));
+            row.insert("protocol".to_string(), Value::String(protocol));
+        }
+    }
+    Some(Value::Object(row))
+}
+
+/// Parses a line with the named capture groups of the regex.
+fn parse_regex(regex: &Regex, line: &str) -> Option<Value> {
+    let caps = regex.captures(line)?;
+    let mut row = Map::new();
+    insert_captures(&mut row, regex, &caps, |_| true);
+    Some(Value::Object(row))
+}
+
+/// Inserts the matched named capture groups that satisfy the filter as string fields.
+fn insert_captures(
+    row: &mut Map<String, Value>,
+    regex: &Regex,
+    caps: &Captures,
+    filter: impl Fn(&str) -> bool,
+) {
+    for name in regex.capture_names().flatten() {
+        if let Some(m) = caps.name(name)
+            && filter(m.as_str())
+        {
+            row.insert(name.to_string(), Value::from(m.as_str()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_logfmt() {
+        assert_eq!(
+            parse_logfmt(r#"level=info msg="hello \"world\"" empty= verbose"#),
+            Some(json!({"level": "info", "msg": "hello \"world\"", "empty": "", "verbose": true}))
+        );
+        assert_eq!(parse_logfmt(r#"msg="unterminated"#), None);
+        assert_eq!(parse_logfmt("=value"), None);
+    }
+
+    #[test]
+    fn test_parse_syslog() {
+        assert_eq!(
+            parse_syslog(
+                r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="App\"lication"][examplePriority@32473 class="high"] An application event"#
+            ),
+            Some(json!({
+                "facility": 20,
+                "severity": 5,
+                "version": 1,
+                "timestamp": "2003-10-11T22:14:15.003Z",
+                "hostname": "mymachine.example.com",
+                "appname": "evntslog",
+                "msgid": "ID47",
+                "structured_data": {
+                    "exampleSDID@32473": {"iut": "3", "eventSource": "App\"lication"},
+                    "examplePriority@32473": {"class": "high"},
+                },
+                "message": "An application event",
+            }))
+        );
+        assert_eq!(
+            parse_syslog("<34>1 2003-10-11T22:14:15.003Z host su - - -"),
+            Some(json!({
+                "facility": 4,
+                "severity": 2,
+                "version": 1,
+                "timestamp": "2003-10-11T22:14:15.003Z",
+                "hostname": "host",
+                "appname": "su",
+            }))
+        );
+        assert_eq!(
+            parse_syslog("<34>Oct 11 22:14:15 mymachine su: 'su root' failed"),
+            Some(json!({
+                "facility": 4,
+                "severity": 2,
+                "timestamp": "Oct 11 22:14:15",
+                "hostname": "mymachine",
+                "appname": "su",
+                "message": "'su root' failed",
+            }))
+        );
+        assert_eq!(
+            parse_syslog("Feb  3 08:00:01 web-1 sshd[1234]: Accepted publickey"),
+            Some(json!({
+                "timestamp": "Feb  3 08:00:01",
+                "hostname": "web-1",
+                "appname": "sshd",
+                "procid": "1234",
+                "message": "Accepted publickey",
+            }))
+        );
+        assert_eq!(parse_syslog("<999>1 - - - - - -"), None);
+        assert_eq!(parse_syslog("not a syslog line"), None);
+    }
+
+    #[test]
+    fn test_parse_clf() {
+        assert_eq!(
+            parse_clf(
+                r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08""#
+            ),
+            Some(json!({
+                "remote_host": "127.0.0.1",
+                "user": "frank",
+                "timestamp": "2000-10-10T20:55:36Z",
+                "request": "GET /apache_pb.gif HTTP/1.0",
+                "method": "GET",
+                "path": "/apache_pb.gif",
+                "protocol": "HTTP/1.0",
+                "status": 200,
+                "bytes": 2326,
+                "referer": "http://www.example.com/start.html",
+                "user_agent": "Mozilla/4.08",
+            }))
+        );
+        assert_eq!(
+            parse_clf(r#"::1 - - [10/Oct/2000:13:55:36 +0000] "-" 400 -"#),
+            Some(json!({
+                "remote_host": "::1",
+                "timestamp": "2000-10-10T13:55:36Z",
+                "status": 400,
+            }))
+        );
+        assert_eq!(parse_clf("GET / HTTP/1.1"), None);
+    }
+
+    #[test]
+    fn test_log_reader() {
+        let data = "a=1\n\nnot logfmt =\nb=2\n";
+        let rows = LogReader::new(data.as_bytes(), LogFormat::Logfmt)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Row::Valid(json!({"a": "1"})),
+                Row::Rejected(RejectedRow {
+                    line: Some(3),
+                    reason: "failed to parse log line".to_string(),
+                    raw: Some("not logfmt =".to_string()),
+                }),
+                Row::Valid(json!({"b": "2"})),
+            ]
+        );
+
+        let regex =
+            Regex::new(r"^(?P<level>[A-Z]+) (?:\[(?P<module>\w+)\] )?(?P<message>.*)$").unwrap();
+        let data = "INFO [main] started\nWARN disk full\n";
+        let rows = LogReader::new(data.as_bytes(), LogFormat::Regex(regex))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Row::Valid(json!({"level": "INFO", "module": "main", "message": "started"})),
+                Row::Valid(json!({"level": "WARN", "message": "disk full"})),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1055 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use indicatif::HumanCount;
+use indicatif::MultiProgress;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use crate::Error;
+use crate::client::IngestType;
+use crate::client::ScopeQLClient;
+use crate::client::StatementOutput;
+use crate::config::Config;
+use crate::global;
+use crate::load::arrow::read_ipc_rows;
+use crate::load::arrow::read_parquet_rows;
+use crate::load::compress::decompress;
+use crate::load::compress::strip_extension;
+pub use crate::load::csv::CsvOptions;
+use crate::load::csv::CsvReader;
+pub use crate::load::follow::FollowOptions;
+use crate::load::follow::follow;
+pub use crate::load::infer::InferOptions;
+pub use crate::load::infer::infer;
+use crate::load::json::JsonLayout;
+pub use crate::load::json::JsonOptions;
+use crate::load::json::read_json_rows;
+use crate::load::logs::LogFormat;
+pub use crate::load::logs::LogOptions;
+use crate::load::logs::LogReader;
+use crate::load::otlp::read_otlp_rows;
+use crate::load::preview::preview;
+pub use crate::load::reject::ErrorOptions;
+use crate::load::reject::RejectFile;
+use crate::load::reject::RejectedRow;
+pub use crate::load::relay::RelayOptions;
+pub use crate::load::relay::serve_ingest;
+use crate::load::resume::CommitTracker;
+use crate::load::resume::LoadState;
+use crate::load::throttle::RateLimiter;
+
+mod arrow;
+mod compress;
+mod csv;
+mod follow;
+mod infer;
+mod json;
+mod logs;
+mod otlp;
+mod preview;
+mod reject;
+mod relay;
+mod resume;
+mod spool;
+mod throttle;
+
+/// Maximum number of rejected rows of a file logged; the rest are only counted.
+const MAX_LOGGED_REJECTIONS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DataFormat {
+    Csv,
+    /// JSON values, either in a top-level array or as a stream.
+    Json,
+    /// A single top-level JSON array of rows.
+    JsonArray,
+    /// Newline-delimited JSON, with one value per line.
+    #[value(alias = "jsonl")]
+    Ndjson,
+    /// A stream of JSON values, e.g., concatenated pretty-printed objects.
+    JsonStream,
+    Parquet,
+    /// The Arrow IPC file or stream format.
+    #[value(alias = "arrow")]
+    ArrowIpc,
+    /// Lines of logfmt key-value pairs.
+    Logfmt,
+    /// RFC 5424 or RFC 3164 (BSD) syslog lines.
+    Syslog,
+    /// Apache/Nginx common or combined log format lines.
+    #[value(alias = "combined")]
+    Clf,
+    /// Lines parsed with the regular expression given by `--regex`.
+    Regex,
+    /// OpenTelemetry (OTLP) logs or traces JSON exports, flattened into a row per log record
+    /// or span.
+    OtlpJson,
+}
+
+/// How ingested rows are committed on the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum IngestMode {
+    /// Each batch is committed before its request returns, so that loaded rows are visible to
+    /// queries once the load completes.
+    Committed,
+    /// Each batch is appended to the server-side ingest buffer, which is committed
+    /// asynchronously in larger transactions. This gives higher throughput for high-volume
+    /// ingestion, but loaded rows become visible to queries only after the buffer is committed.
+    Buffered,
+}
+
+impl From<IngestMode> for IngestType {
+    fn from(mode: IngestMode) -> Self {
+        match mode {
+            IngestMode::Committed => IngestType::Committed,
+            IngestMode::Buffered => IngestType::Buffered,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Ingest Options")]
+pub struct IngestOptions {
+    /// How ingested rows are committed on the server.
+    #[clap(long, value_enum, value_name = "MODE", default_value = "committed")]
+    pub mode: IngestMode,
+    /// Maximum number of rows in a batch.
+    #[clap(long, value_name = "ROWS", default_value = "100000")]
+    pub batch_rows: usize,
+    /// Maximum size of a batch, e.g., `16MiB`.
+    ///
+    /// A batch is sent once it reaches either the row or the size limit.
+    #[clap(long, value_name = "SIZE", default_value = "16MiB", value_parser = parse_size)]
+    pub batch_size: usize,
+    /// Maximum number of batches in flight; `1` sends batches sequentially.
+    #[clap(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+    /// Maximum number of files loaded in parallel, each with up to `--concurrency` batches in
+    /// flight.
+    #[clap(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: u16,
+    /// Maximum number of rows sent per second, shared by all files of the load.
+    ///
+    /// Batches are delayed to keep the average rate under the limit, so that bulk loads can run
+    /// alongside production traffic.
+    #[clap(long, value_name = "ROWS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_rows_per_sec: Option<u64>,
+    /// Maximum size of rows sent per second, e.g., `8MiB`, shared by all files of the load.
+    #[clap(long, value_name = "SIZE", value_parser = parse_nonzero_size)]
+    pub max_bytes_per_sec: Option<usize>,
+    /// Record the batches committed in a sidecar state file (`<FILE>.scopeql-state`), and
+    /// continue from the last committed batch if the state file exists, e.g., after a failure.
+    ///
+    /// The state file is removed once the file is loaded completely. With `--concurrency`
+    /// greater than 1, batches committed after a failed batch are sent again when resumed.
+    /// Committed rows are read and parsed again to be skipped, so resuming takes time
+    /// proportional to the number of rows already committed.
+    #[clap(long)]
+    pub resume: bool,
+    #[clap(flatten)]
+    pub error_options: ErrorOptions,
+}
+
+/// Options of how the source data is read.
+#[derive(Debug, Clone, clap::Args)]
+pub struct FormatOptions {
+    /// The source data format.
+    ///
+    /// Detected by the file extension if not specified.
+    #[clap(long, value_enum)]
+    pub format: Option<DataFormat>,
+    /// The columns to load from Parquet or Arrow IPC files, separated by commas; all columns
+    /// are loaded if not specified. Other formats are rejected with this option.
+    #[clap(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Vec<String>,
+    #[clap(flatten)]
+    pub csv_options: CsvOptions,
+    #[clap(flatten)]
+    pub json_options: JsonOptions,
+    #[clap(flatten)]
+    pub log_options: LogOptions,
+}
+
+/// A row read from a data source.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Row {
+    Valid(Value),
+    /// A malformed row; reading continues with the next row.
+    Rejected(RejectedRow),
+}
+
+/// A reader of rows from a data source.
+type RowReader = Box<dyn Iterator<Item = Result<Row, Error>>>;
+
+/// The numbers of rows inserted and rejected by a load.
+#[derive(Debug, Default, Clone, Copy)]
+struct LoadStats {
+    rows_inserted: u64,
+    rows_rejected: u64,
+}
+
+pub fn load(
+    config: &Config,
+    files: Vec<PathBuf>,
+    transform: String,
+    preview_rows: Option<usize>,
+    format_options: FormatOptions,
+    ingest_options: IngestOptions,
+    follow_options: FollowOptions,
+) {
+    let connection = config
+        .get_default_connection()
+        .expect("no default connection in config");
+    let client = ScopeQLClient::new(connection.endpoint().to_owned())
+        .with_compression(connection.compression());
+    let client = Arc::new(client);
+
+    let files = expand_files(files).unwrap_or_else(|err| {
+        log::error!("{err:?}");
+        std::process::exit(1);
+    });
+
+    let mut sources = Vec::with_capacity(files.len());
+    for file in files {
+        let format = resolve_format(&file, &format_options).unwrap_or_else(|err| {
+            log::error!("{err:?}");
+            std::process::exit(1);
+        });
+        sources.push((file, format));
+    }
+
+    if let Some(limit) = preview_rows {
+        let (file, format) = &sources[0];
+        if sources.len() > 1 {
+            log::info!("previewing the first file: {}", display_name(file));
+        }
+        let result = global::rt().block_on(preview(
+            &client,
+            file,
+            *format,
+            &transform,
+            &format_options,
+            &ingest_options.error_options,
+            limit,
+        ));
+        match result {
+            Ok(StatementOutput::Finished(output)) => println!("{output}"),
+            Ok(output) => {
+                log::error!("failed to preview the transform: {output}");
+                std::process::exit(1);
+            }
+            Err(err) => {
+                log::error!("failed to preview the transform: {err:?}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if follow_options.follow {
+        let [(file, format)] = &sources[..] else {
+            log::error!("--follow requires exactly one file");
+            std::process::exit(1);
+        };
+        if is_stdin(file) {
+            log::error!("--follow cannot be used with stdin");
+            std::process::exit(1);
+        }
+
+        let result = global::rt().block_on(follow(
+            &client,
+            file,
+            *format,
+            transform,
+            &format_options,
+            &ingest_options,
+            &follow_options,
+        ));
+        match result {
+            Ok(n) => {
+                let stats = LoadStats {
+                    rows_inserted: n,
+                    rows_rejected: 0,
+                };
+                log::info!("{}", describe_result(ingest_options.mode, stats))
+            }
+            Err(err) => {
+                log::error!("failed to load data: {err:?}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let reject_file = match &ingest_options.error_options.reject_file {
+        Some(path) => match RejectFile::open(path.clone(), ingest_options.resume) {
+            Ok(reject_file) => Some(reject_file),
+            Err(err) => {
+                log::error!("{err:?}");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let loader = Loader {
+        client,
+        transform: &transform,
+        format_options: &format_options,
+        ingest_options: &ingest_options,
+        reject_file: reject_file.as_ref(),
+        rate_limiter: RateLimiter::new(&ingest_options),
+    };
+
+    let multiple = sources.len() > 1;
+    let mp = MultiProgress::new();
+    let queue = Mutex::new(sources.iter());
+    let results = Mutex::new(vec![]);
+    std::thread::scope(|s| {
+        for _ in 0..(ingest_options.parallel as usize).min(sources.len()) {
+            s.spawn(|| {
+                loop {
+                    let Some((file, format)) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+
+                    let pb = mp.add(make_progress_bar(file));
+                    if multiple {
+                        pb.set_prefix(display_name(file));
+                    }
+                    let mut stats = LoadStats::default();
+                    let result = loader.load_file(file, *format, &pb, &mut stats);
+                    pb.finish_and_clear();
+                    mp.remove(&pb);
+
+                    if multiple {
+                        mp.suspend(|| match &result {
+                            Ok(()) => log::info!(
+                                "{}: {}",
+                                display_name(file),
+                                describe_result(ingest_options.mode, stats)
+                            ),
+                            Err(err) => log::error!(
+                                "{}: failed to load data after {}: {err:?}",
+                                display_name(file),
+                                describe_ingested(ingest_options.mode, stats)
+                            ),
+                        });
+                    }
+                    results.lock().unwrap().push((stats, result));
+                }
+            });
+        }
+    });
+
+    if let Some(reject_file) = &reject_file
+        && let Err(err) = reject_file.flush()
+    {
+        log::error!("{err:?}");
+        std::process::exit(1);
+    }
+
+    let results = results.into_inner().unwrap();
+    let num_failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    // rows ingested before a failure are counted as well, since they are not rolled back
+    let stats = results
+        .iter()
+        .fold(LoadStats::default(), |acc, (stats, _)| LoadStats {
+            rows_inserted: acc.rows_inserted + stats.rows_inserted,
+            rows_rejected: acc.rows_rejected + stats.rows_rejected,
+        });
+    match results.into_iter().next() {
+        Some((_, Err(err))) if !multiple => {
+            log::error!(
+                "failed to load data after {}: {err:?}",
+                describe_ingested(ingest_options.mode, stats)
+            );
+            std::process::exit(1);
+        }
+        _ if num_failed > 0 => {
+            log::error!(
+                "failed to load {num_failed} of {} files; {} in total",
+                sources.len(),
+                describe_ingested(ingest_options.mode, stats)
+            );
+            std::process::exit(1);
+        }
+        _ => {
+            let result = describe_result(ingest_options.mode, stats);
+            if multiple {
+                log::info!("{} files loaded: {result}", sources.len());
+            } else {
+                log::info!("{result}");
+            }
+            if let Some(reject_file) = &reject_file
+                && stats.rows_rejected > 0
+            {
+                log::warn!(
+                    "malformed rows are written to {}",
+                    reject_file.path().display()
+                );
+            }
+        }
+    }
+}
+
+/// Loads files with the options shared by all of them.
+struct Loader<'a> {
+    client: Arc<ScopeQLClient>,
+    transform: &'a str,
+    format_options: &'a FormatOptions,
+    ingest_options: &'a IngestOptions,
+    reject_file: Option<&'a RejectFile>,
+    rate_limiter: RateLimiter,
+}
+
+impl Loader<'_> {
+    /// Loads a data file, and counts the rows inserted and rejected in `stats`, including those
+    /// before a failure.
+    fn load_file(
+        &self,
+        file: &Path,
+        format: DataFormat,
+        pb: &ProgressBar,
+        stats: &mut LoadStats,
+    ) -> Result<(), Error> {
+        let mut state = match self.ingest_options.resume {
+            true if is_stdin(file) => bail!(Error::new("stdin cannot be resumed".to_string())),
+            true => Some(LoadState::open(file, self.transform)?),
+            false => None,
+        };
+
+        let mut rows = open_rows(file, format, self.format_options.clone(), pb.clone())?;
+        if let Some(skipped) = state.as_ref().map(LoadState::rows_committed)
+            && skipped > 0
+        {
+            pb.suspend(|| {
+                log::info!(
+                    "{}: resuming; skipping {skipped} row(s) committed by the previous load",
+                    display_name(file)
+                )
+            });
+            // rows have no stable offsets across formats and compressions, so the committed
+            // rows are parsed again and skipped, in O(rows committed)
+            rows = Box::new(rows.skip(skipped as usize));
+        }
+
+        let ingest = self.ingest(file, rows, pb, state.as_mut(), stats);
+        global::rt().block_on(ingest).inspect_err(|_| {
+            if state.is_some() {
+                pb.suspend(|| {
+                    log::info!(
+                        "{}: the committed batches are recorded; rerun with --resume to continue",
+                        display_name(file)
+                    )
+                });
+            }
+        })?;
+        if let Some(state) = state {
+            state.remove()?;
+        }
+        Ok(())
+    }
+
+    /// Reads rows and ingests them in batches, and counts the rows inserted and rejected in
+    /// `stats`.
+    async fn ingest(
+        &self,
+        file: &Path,
+        rows: RowReader,
+        pb: &ProgressBar,
+        mut state: Option<&mut LoadState>,
+        stats: &mut LoadStats,
+    ) -> Result<(), Error> {
+        let options = self.ingest_options;
+        let verb = match options.mode {
+            IngestMode::Committed => "inserted",
+            IngestMode::Buffered => "buffered",
+        };
+
+        let mut tasks = JoinSet::new();
+        let mut batch = String::new();
+        let mut batch_rows = 0;
+        let mut batch_rows_read = 0;
+        let mut num_batches = 0;
+        let mut tracker = CommitTracker::default();
+        let rows_skipped = state.as_ref().map_or(0, |state| state.rows_committed());
+        let mut on_complete = |(seq, rows, inserted): BatchResult| -> Result<(), Error> {
+            stats.rows_inserted += inserted;
+            pb.set_message(format!("{} rows {verb}", HumanCount(stats.rows_inserted)));
+            let rows_committed = tracker.complete(seq, rows);
+            if let Some(state) = state.as_mut() {
+                state.save(rows_skipped + rows_committed)?;
+            }
+            Ok(())
+        };
+
+        let mut rows = rows.peekable();
+        let mut result: Result<(), Error> = async {
+            while let Some(row) = rows.next() {
+                batch_rows_read += 1;
+                match row? {
+                    Row::Valid(row) => {
+                        writeln!(&mut batch, "{row}").unwrap();
+                        batch_rows += 1;
+                    }
+                    Row::Rejected(row) => {
+                        stats.rows_rejected += 1;
+                        self.reject(file, &row, stats.rows_rejected, pb)?;
+                    }
+                }
+
+                let is_last = rows.peek().is_none();
+                if batch_rows == 0
+                    || (batch_rows < options.batch_rows
+                        && batch.len() < options.batch_size
+                        && !is_last)
+                {
+                    continue;
+                }
+
+                while tasks.len() >= options.concurrency as usize {
+                    on_complete(join_next(&mut tasks).await?)?;
+                }
+
+                let client = self.client.clone();
+                let transform = self.transform.to_string();
+                let ty = options.mode.into();
+                let data = std::mem::take(&mut batch);
+                let seq = num_batches;
+                let rows = std::mem::take(&mut batch_rows_read);
+                self.rate_limiter
+                    .acquire(batch_rows as u64, data.len() as u64)
+                    .await;
+                batch_rows = 0;
+                num_batches += 1;
+                tasks.spawn(async move {
+                    let result = client.load_jsonlines(data, transform, ty).await?;
+                    Ok((seq, rows, result.num_rows_inserted as u64))
+                });
+            }
+            Ok(())
+        }
+        .await;
+
+        // wait for the batches in flight even if the load has failed, since they may be
+        // committed on the server, and must be recorded so that a resumed load skips them
+        while !tasks.is_empty() {
+            let completed = join_next(&mut tasks).await.and_then(&mut on_complete);
+            if let Err(err) = completed
+                && result.is_ok()
+            {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    /// Writes the rejected row to the reject file if any, and fails once the number of rejected
+    /// rows of the file exceeds `--max-errors`.
+    fn reject(
+        &self,
+        file: &Path,
+        row: &RejectedRow,
+        num_rows_rejected: u64,
+        pb: &ProgressBar,
+    ) -> Result<(), Error> {
+        let name = display_name(file);
+        if let Some(reject_file) = self.reject_file {
+            reject_file.write(&name, row)?;
+        }
+
+        let error_options = &self.ingest_options.error_options;
+        error_options.check_max_errors(row, num_rows_rejected)?;
+
+        if num_rows_rejected <= MAX_LOGGED_REJECTIONS {
+            pb.suspend(|| {
+                log::warn!("{name}: rejected a malformed row ({row})");
+                if num_rows_rejected == MAX_LOGGED_REJECTIONS {
+                    log::warn!("{name}: further malformed rows are not logged");
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+fn describe_result(mode: IngestMode, stats: LoadStats) -> String {
+    let result = match (mode, stats.rows_inserted) {
+        (_, 0) => "no rows were inserted".to_string(),
+        (IngestMode::Committed, 1) => "successfully inserted 1 row".to_string(),
+        (IngestMode::Committed, n) => format!("successfully inserted {n} rows"),
+        (IngestMode::Buffered, n) => format!(
+            "successfully buffered {n} row(s); they will be visible to queries once the server commits the buffer"
+        ),
+    };
+    match stats.rows_rejected {
+        0 => result,
+        n => format!("{result}; rejected {n} malformed row(s)"),
+    }
+}
+
+/// Describes the rows ingested by a load that has failed, e.g., `10 row(s) inserted`.
+fn describe_ingested(mode: IngestMode, stats: LoadStats) -> String {
+    let verb = match mode {
+        IngestMode::Committed => "inserted",
+        IngestMode::Buffered => "buffered",
+    };
+    format!("{} row(s) {verb}", stats.rows_inserted)
+}
+
+/// Returns whether the file path denotes the standard input.
+fn is_stdin(file: &Path) -> bool {
+    file.as_os_str() == "-"
+}
+
+fn display_name(file: &Path) -> String {
+    if is_stdin(file) {
+        "<stdin>".to_string()
+    } else {
+        file.display().to_string()
+    }
+}
+
+/// Expands glob patterns in the file paths, keeping the order and skipping duplicates.
+fn expand_files(patterns: Vec<PathBuf>) -> Result<Vec<PathBuf>, Error> {
+    let mut files = vec![];
+    for pattern in patterns {
+        let is_pattern = pattern
+            .to_str()
+            .is_some_and(|s| s.contains(['*', '?', '[']));
+        if !is_pattern || pattern.exists() {
+            files.push(pattern);
+            continue;
+        }
+
+        let pattern = pattern.to_str().unwrap();
+        let make_error = || Error::new(format!("invalid glob pattern: {pattern}"));
+        let mut matches = glob::glob(pattern)
+            .or_raise(make_error)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .or_raise(make_error)?;
+        if matches.is_empty() {
+            bail!(Error::new(format!("no files match the pattern: {pattern}")));
+        }
+        matches.sort();
+        files.extend(matches);
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    if files.len() > 1 && files.iter().any(|file| is_stdin(file)) {
+        bail!(Error::new(
+            "stdin cannot be loaded together with other files".to_string()
+        ));
+    }
+    Ok(files)
+}
+
+/// Returns the data format given by the options, or detected by the file extension.
+fn resolve_format(file: &Path, format_options: &FormatOptions) -> Result<DataFormat, Error> {
+    let format = match format_options.format {
+        Some(format) => format,
+        None if format_options.log_options.regex.is_some() => DataFormat::Regex,
+        None if is_stdin(file) => bail!(Error::new(
+            "the --format option is required when loading from stdin".to_string()
+        )),
+        None => match detect_format(file) {
+            Some(format) => format,
+            None => bail!(Error::new(format!(
+                "unknown data file format: {}; specify the format using the --format option",
+                file.display()
+            ))),
+        },
+    };
+
+    if !format_options.columns.is_empty()
+        && !matches!(format, DataFormat::Parquet | DataFormat::ArrowIpc)
+    {
+        bail!(Error::new(format!(
+            "the --columns option is only supported for parquet and arrow-ipc files, but {} is loaded as {format:?}",
+            display_name(file)
+        )));
+    }
+    Ok(format)
+}
+
+/// Detects the data format by the file extension, ignoring the compression extension.
+fn detect_format(file: &Path) -> Option<DataFormat> {
+    match strip_extension(file).extension()?.to_str()? {
+        "json" => Some(DataFormat::Json),
+        "ndjson" | "jsonl" => Some(DataFormat::Ndjson),
+        "csv" => Some(DataFormat::Csv),
+        "parquet" => Some(DataFormat::Parquet),
+        "arrow" | "arrows" | "feather" | "ipc" => Some(DataFormat::ArrowIpc),
+        _ => None,
+    }
+}
+
+fn make_progress_bar(file: &Path) -> ProgressBar {
+    let pb_style = "{spinner:.green} {prefix} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) {msg}";
+    let pb = match std::fs::metadata(file) {
+        Ok(metadata) if !is_stdin(file) => ProgressBar::new(metadata.len()),
+        _ => ProgressBar::no_length(),
+    };
+    pb.with_style(ProgressStyle::with_template(pb_style).unwrap())
+}
+
+fn open_rows(
+    file: &Path,
+    format: DataFormat,
+    options: FormatOptions,
+    pb: ProgressBar,
+) -> Result<RowReader, Error> {
+    let make_error = || Error::new(format!("failed to open data file: {}", file.display()));
+    let FormatOptions {
+        columns,
+        csv_options,
+        json_options,
+        log_options,
+        ..
+    } = options;
+
+    if format == DataFormat::Parquet {
+        // Parquet files are compressed internally and need random access.
+        if is_stdin(file) {
+            bail!(Error::new(
+                "parquet data cannot be loaded from stdin".to_string()
+            ));
+        }
+        let rows = read_parquet_rows(file, &columns, pb)?;
+        return Ok(Box::new(rows.map(|row| row.map(Row::Valid))));
+    }
+
+    let reader: Box<dyn Read + Send> = if is_stdin(file) {
+        Box::new(std::io::stdin())
+    } else {
+        Box::new(File::open(file).or_raise(make_error)?)
+    };
+    let reader = BufReader::new(ProgressReader { inner: reader, pb });
+    let reader = decompress(file, reader)?;
+    let rows: RowReader = match format {
+        DataFormat::Csv => Box::new(CsvReader::new(reader, csv_options).or_raise(make_error)?),
+        DataFormat::Json => read_json_rows(reader, JsonLayout::Auto, json_options)?,
+        DataFormat::JsonArray => read_json_rows(reader, JsonLayout::Array, json_options)?,
+        DataFormat::Ndjson => read_json_rows(reader, JsonLayout::Lines, json_options)?,
+        DataFormat::JsonStream => read_json_rows(reader, JsonLayout::Stream, json_options)?,
+        DataFormat::ArrowIpc => {
+            let rows = read_ipc_rows(reader, &columns)?;
+            Box::new(rows.map(|row| row.map(Row::Valid)))
+        }
+        DataFormat::Parquet => unreachable!("parquet files are read above"),
+        DataFormat::Logfmt => Box::new(LogReader::new(reader, LogFormat::Logfmt)),
+        DataFormat::Syslog => Box::new(LogReader::new(reader, LogFormat::Syslog)),
+        DataFormat::Clf => Box::new(LogReader::new(reader, LogFormat::Clf)),
+        DataFormat::Regex => match log_options.regex {
+            Some(regex) => Box::new(LogReader::new(reader, LogFormat::Regex(regex))),
+            None => bail!(Error::new(
+                "the regex format requires the --regex option".to_string()
+            )),
+        },
+        DataFormat::OtlpJson => read_otlp_rows(reader, JsonLayout::Auto)?,
+    };
+    Ok(rows)
+}
+
+/// The sequence number, the number of rows read including rejected ones, and the number of rows
+/// inserted of a batch.
+type BatchResult = (u64, u64, u64);
+
+/// Waits for the next ingest task to complete.
+async fn join_next(tasks: &mut JoinSet<Result<BatchResult, Error>>) -> Result<BatchResult, Error> {
+    match tasks.join_next().await {
+        Some(result) => result.or_raise(|| Error::new("failed to join ingest task".to_string()))?,
+        None => bail!(Error::new("no ingest task to join".to_string())),
+    }
+}
+
+/// Writes the file by renaming a temporary file, so that it is never partially written.
+fn write_file_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let make_error = || Error::new(format!("failed to write file: {}", path.display()));
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, content).or_raise(make_error)?;
+    std::fs::rename(&tmp, path).or_raise(make_error)
+}
+
+/// A reader that advances the progress bar by the number of bytes read.
+struct ProgressReader<R> {
+    inner: R,
+    pb: ProgressBar,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pb.inc(n as u64);
+        Ok(n)
+    }
+}
+
+/// Parses a non-negative duration, e.g., `500ms`, `1s` or `1m`.
+fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let duration = s
+        .parse::<jiff::SignedDuration>()
+        .map_err(|err| format!("invalid duration {s:?}: {err}"))?;
+    Duration::try_from(duration).map_err(|err| format!("invalid duration {s:?}: {err}"))
+}
+
+/// Parses a size in bytes with an optional binary unit, e.g., `1024`, `64KiB` or `16MiB`.
+fn parse_size(s: &str) -> std::result::Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value
+        .parse::<usize>()
+        .map_err(|err| format!("invalid size {s:?}: {err}"))?;
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        unit => return Err(format!("invalid size unit {unit:?} in {s:?}")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size {s:?} is too large"))
+}
+
+/// Parses a size like [`parse_size`], and rejects zero.
+fn parse_nonzero_size(s: &str) -> std::result::Result<usize, String> {
+    match parse_size(s)? {
+        0 => Err(format!("size {s:?} must be greater than zero")),
+        size => Ok(size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::MockServer;
+
+    fn format_options(format: Option<DataFormat>, columns: &[&str]) -> FormatOptions {
+        FormatOptions {
+            format,
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            csv_options: CsvOptions::default(),
+            json_options: JsonOptions { json_path: None },
+            log_options: LogOptions { regex: None },
+        }
+    }
+
+    #[test]
+    fn test_expand_files() {
+        let dir = std::env::temp_dir().join(format!("scopeql-expand-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.json", "a.json", "c.csv", "x*.json"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        // literal paths keep their order, and matches of a pattern are sorted; duplicates are
+        // skipped
+        let files = expand_files(vec![dir.join("b.json"), dir.join("*.json")]).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.join("b.json"), dir.join("a.json"), dir.join("x*.json")]
+        );
+
+        // an existing file is not expanded even if its name contains a glob character
+        let files = expand_files(vec![dir.join("x*.json")]).unwrap();
+        assert_eq!(files, vec![dir.join("x*.json")]);
+
+        assert!(expand_files(vec![dir.join("*.parquet")]).is_err());
+
+        // stdin can only be loaded alone
+        let stdin = PathBuf::from("-");
+        let files = expand_files(vec![stdin.clone(), stdin.clone()]).unwrap();
+        assert_eq!(files, vec![stdin.clone()]);
+        assert!(expand_files(vec![stdin, dir.join("c.csv")]).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |file: &str| detect_format(Path::new(file));
+        assert_eq!(detect("a.csv"), Some(DataFormat::Csv));
+        assert_eq!(detect("logs/a.jsonl"), Some(DataFormat::Ndjson));
+        assert_eq!(detect("a.feather"), Some(DataFormat::ArrowIpc));
+        // the compression extension is ignored
+        assert_eq!(detect("a.csv.gz"), Some(DataFormat::Csv));
+        assert_eq!(detect("a.json.zst"), Some(DataFormat::Json));
+        assert_eq!(detect("a.gz"), None);
+        assert_eq!(detect("a.txt"), None);
+        assert_eq!(detect("-"), None);
+    }
+
+    #[test]
+    fn test_resolve_format() {
+        let options = format_options(None, &[]);
+        let file = Path::new("a.ndjson.xz");
+        assert_eq!(resolve_format(file, &options).unwrap(), DataFormat::Ndjson);
+        assert!(resolve_format(Path::new("a.log"), &options).is_err());
+        assert!(resolve_format(Path::new("-"), &options).is_err());
+
+        // the given format overrides the file extension
+        let options = format_options(Some(DataFormat::Logfmt), &[]);
+        assert_eq!(resolve_format(file, &options).unwrap(), DataFormat::Logfmt);
+        assert_eq!(
+            resolve_format(Path::new("-"), &options).unwrap(),
+            DataFormat::Logfmt
+        );
+
+        // --regex implies the regex format
+        let mut options = format_options(None, &[]);
+        options.log_options.regex = Some(regex::Regex::new("(?P<message>.*)").unwrap());
+        assert_eq!(
+            resolve_format(Path::new("a.log"), &options).unwrap(),
+            DataFormat::Regex
+        );
+    }
+
+    #[test]
+    fn test_resolve_format_columns() {
+        let options = format_options(None, &["id"]);
+        assert_eq!(
+            resolve_format(Path::new("a.parquet"), &options).unwrap(),
+            DataFormat::Parquet
+        );
+        assert_eq!(
+            resolve_format(Path::new("a.arrow"), &options).unwrap(),
+            DataFormat::ArrowIpc
+        );
+        assert!(resolve_format(Path::new("a.csv"), &options).is_err());
+        let options = format_options(Some(DataFormat::Ndjson), &["id"]);
+        assert!(resolve_format(Path::new("-"), &options).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_max_errors_in_flight() {
+        #[derive(clap::Parser)]
+        struct Args {
+            #[clap(flatten)]
+            ingest_options: IngestOptions,
+        }
+        let args = <Args as clap::Parser>::parse_from([
+            "scopeql",
+            "--batch-rows",
+            "1",
+            "--concurrency",
+            "4",
+        ]);
+        let server = MockServer::start().await;
+        let format_options = format_options(None, &[]);
+        let loader = Loader {
+            client: Arc::new(ScopeQLClient::new(server.endpoint().to_string())),
+            transform: "INSERT INTO t",
+            format_options: &format_options,
+            ingest_options: &args.ingest_options,
+            reject_file: None,
+            rate_limiter: RateLimiter::new(&args.ingest_options),
+        };
+
+        let file =
+            std::env::temp_dir().join(format!("scopeql-ingest-{}.ndjson", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let mut state = LoadState::open(&file, loader.transform).unwrap();
+
+        // the malformed row exceeds the budget while the batches before it are in flight
+        let rejected = RejectedRow {
+            line: Some(4),
+            reason: "malformed".to_string(),
+            raw: None,
+        };
+        let rows: RowReader = Box::new(
+            [
+                Row::Valid(serde_json::json!({"a": 1})),
+                Row::Valid(serde_json::json!({"a": 2})),
+                Row::Valid(serde_json::json!({"a": 3})),
+                Row::Rejected(rejected),
+                Row::Valid(serde_json::json!({"a": 5})),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+        let mut stats = LoadStats::default();
+        let pb = ProgressBar::hidden();
+        let result = loader
+            .ingest(&file, rows, &pb, Some(&mut state), &mut stats)
+            .await;
+        assert!(result.is_err());
+
+        // the batches in flight are counted and recorded
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(stats.rows_inserted, 3);
+        assert_eq!(stats.rows_rejected, 1);
+        assert_eq!(state.rows_committed(), 3);
+
+        state.remove().unwrap();
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("64KiB"), Ok(64 << 10));
+        assert_eq!(parse_size("16 MB"), Ok(16 << 20));
+        assert!(parse_size("1TiB").is_err());
+        assert!(parse_size("MiB").is_err());
+
+        assert_eq!(parse_nonzero_size("1KiB"), Ok(1 << 10));
+        assert!(parse_nonzero_size("0").is_err());
+        assert!(parse_nonzero_size("0MiB").is_err());
+    }
+
+    #[test]
+    fn test_max_bytes_per_sec() {
+        #[derive(clap::Parser)]
+        struct Args {
+            #[clap(flatten)]
+            ingest_options: IngestOptions,
+        }
+        let parse = |value: &str| {
+            <Args as clap::Parser>::try_parse_from(["scopeql", "--max-bytes-per-sec", value])
+                .map(|args| args.ingest_options.max_bytes_per_sec)
+        };
+        assert_eq!(parse("8MiB").unwrap(), Some(8 << 20));
+        assert!(parse("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("soon").is_err());
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Flattening of OpenTelemetry (OTLP) JSON exports, e.g., written by the file exporter of the
+//! OpenTelemetry Collector, into rows.
+//!
+//! Each log record or span is loaded as a row, with the attributes of its resource and
+//! instrumentation scope, timestamps as RFC 3339 strings, and trace and span IDs as hex
+//! strings.
+
+use std::io::BufRead;
+
+use exn::Result;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::Error;
+use crate::load::Row;
+use crate::load::RowReader;
+use crate::load::json::JsonLayout;
+use crate::load::json::JsonOptions;
+use crate::load::json::read_json_rows;
+use crate::load::reject::RejectedRow;
+
+/// Reads OTLP export requests in the given JSON layout, and flattens them into rows; a value
+/// that is not an OTLP logs or traces export is rejected.
+pub fn read_otlp_rows<R: BufRead + 'static>(
+    reader: R,
+    layout: JsonLayout,
+) -> Result<RowReader, Error> {
+    let values = read_json_rows(reader, layout, JsonOptions { json_path: None })?;
+    Ok(Box::new(values.flat_map(|row| {
+        let rows: Vec<Result<Row, Error>> = match row {
+            Ok(Row::Valid(value)) => match flatten_otlp(&value) {
+                Ok(rows) => rows.into_iter().map(|row| Ok(Row::Valid(row))).collect(),
+                Err(reason) => vec![Ok(Row::Rejected(RejectedRow {
+                    line: None,
+                    reason,
+                    raw: Some(value.to_string()),
+                }))],
+            },
+            row => vec![row],
+        };
+        rows
+    })))
+}
+
+/// Flattens an OTLP logs or traces export request into rows of log records or spans.
+pub fn flatten_otlp(request: &Value) -> std::result::Result<Vec<Value>, String> {
+    if let Some(resource_logs) = request.get("resourceLogs") {
+        flatten_resources(resource_logs, "scopeLogs", "logRecords", flatten_log_record)
+    } else if let Some(resource_spans) = request.get("resourceSpans") {
+        flatten_resources(resource_spans, "scopeSpans", "spans", flatten_span)
+    } else {
+        Err("expected an OTLP export with `resourceLogs` or `resourceSpans`".to_string())
+    }
+}
+
+fn flatten_resources(
+    resources: &Value,
+    scopes_key: &str,
+    items_key: &str,
+    flatten_item: fn(&Value, &mut Map<String, Value>),
+) -> std::result::Result<Vec<Value>, String> {
+    let mut rows = vec![];
+    for resource in as_array(resources, "resources")? {
+        let resource_attributes = resource
+            .get("resource")
+            .map_or_else(Map::new, |resource| attributes(resource.get("attributes")));
+        let schema_url = resource.get("schemaUrl").cloned();
+
+        for scope in as_array_or_empty(resource.get(scopes_key), scopes_key)? {
+            let scope_info = scope.get("scope");
+            let scope_field = |key: &str| {
+                scope_info
+                    .and_then(|scope| scope.get(key))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            };
+
+            for item in as_array_or_empty(scope.get(items_key), items_key)? {
+                let mut row = Map::new();
+                row.insert(
+                    "resource".to_string(),
+                    Value::Object(resource_attributes.clone()),
+                );
+                if let Some(schema_url) = &schema_url {
+                    row.insert("schema_url".to_string(), schema_url.clone());
+                }
+                row.insert("scope_name".to_string(), scope_field("name"));
+                row.insert("scope_version".to_string(), scope_field("version"));
+                let scope_attributes = attributes(scope_info.and_then(|s| s.get("attributes")));
+                if !scope_attributes.is_empty() {
+                    row.insert(
+                        "scope_attributes".to_string(),
+                        Value::Object(scope_attributes),
+                    );
+                }
+                flatten_item(item, &mut row);
+                rows.push(Value::Object(row));
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn flatten_log_record(record: &Value, row: &mut Map<String, Value>) {
+    let time = timestamp(record.get("timeUnixNano"));
+    let observed_time = timestamp(record.get("observedTimeUnixNano"));
+    // the time of the event may be unknown, in which case the observed time is used
+    let timestamp = if time.is_null() {
+        observed_time.clone()
+    } else {
+        time
+    };
+    row.insert("timestamp".to_string(), timestamp);
+    row.insert("observed_timestamp".to_string(), observed_time);
+    row.insert(
+        "severity_number".to_string(),
+        record.get("severityNumber").cloned().unwrap_or(Value::Null),
+    );
+    row.insert(
+        "severity_text".to_string(),
+        record.get("severityText").cloned().unwrap_or(Value::Null),
+    );
+    row.insert(
+        "body".to_string(),
+        record.get("body").map_or(Value::Null, any_value),
+    );
+    row.insert(
+        "attributes".to_string(),
+        Value::Object(attributes(record.get("attributes"))),
+    );
+    row.insert("trace_id".to_string(), id(record.get("traceId")));
+    row.insert("span_id".to_string(), id(record.get("spanId")));
+    if let Some(flags) = record.get("flags") {
+        row.insert("flags".to_string(), flags.clone());
+    }
+}
+
+fn flatten_span(span: &Value, row: &mut Map<String, Value>) {
+    row.insert("trace_id".to_string(), id(span.get("traceId")));
+    row.insert("span_id".to_string(), id(span.get("spanId")));
+    row.insert("parent_span_id".to_string(), id(span.get("parentSpanId")));
+    if let Some(trace_state) = span.get("traceState").filter(|s| !is_empty_string(s)) {
+        row.insert("trace_state".to_string(), trace_state.clone());
+    }
+    row.insert(
+        "name".to_string(),
+        span.get("name").cloned().unwrap_or(Value::Null),
+    );
+    row.insert("kind".to_string(), span_kind(span.get("kind")));
+
+    let start = span.get("startTimeUnixNano");
+    let end = span.get("endTimeUnixNano");
+    row.insert("start_timestamp".to_string(), timestamp(start));
+    row.insert("end_timestamp".to_string(), timestamp(end));
+    let duration = match (start.and_then(nanos), end.and_then(nanos)) {
+        (Some(start), Some(end)) if end >= start => Value::from((end - start) as u64),
+        _ => Value::Null,
+    };
+    row.insert("duration_nanos".to_string(), duration);
+
+    row.insert(
+        "attributes".to_string(),
+        Value::Object(attributes(span.get("attributes"))),
+    );
+    let status = span.get("status");
+    row.insert(
+        "status_code".to_string(),
+        status_code(status.and_then(|s| s.get("code"))),
+    );
+    row.insert(
+        "status_message".to_string(),
+        status
+            .and_then(|s| s.get("message"))
+            .filter(|s| !is_empty_string(s))
+            .cloned()
+            .unwrap_or(Value::Null),
+    );
+
+    let events = span
+        .get("events")
+        .and_then(Value::as_array)
+        .map(|events| {
+            events
+                .iter()
+                .map(|event| {
+                    let mut object = Map::new();
+                    object.insert(
+                        "timestamp".to_string(),
+                        timestamp(event.get("timeUnixNano")),
+                    );
+                    object.insert(
+                        "name".to_string(),
+                        event.get("name").cloned().unwrap_or(Value::Null),
+                    );
+                    object.insert(
+                        "attributes".to_string(),
+                        Value::Object(attributes(event.get("attributes"))),
+                    );
+                    Value::Object(object)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    row.insert("events".to_string(), Value::Array(events));
+
+    let links = span
+        .get("links")
+        .and_then(Value::as_array)
+        .map(|links| {
+            links
+                .iter()
+                .map(|link| {
+                    let mut object = Map::new();
+                    object.insert("trace_id".to_string(), id(link.get("traceId")));
+                    object.insert("span_id".to_string(), id(link.get("spanId")));
+                    object.insert(
+                        "attributes".to_string(),
+                        Value::Object(attributes(link.get("attributes"))),
+                    );
+                    Value::Object(object)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    row.insert("links".to_string(), Value::Array(links));
+}
+
+fn as_array<'a>(value: &'a Value, name: &str) -> std::result::Result<&'a [Value], String> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| format!("expected an array of {name} in OTLP export"))
+}
+
+fn as_array_or_empty<'a>(
+    value: Option<&'a Value>,
+    name: &str,
+) -> std::result::Result<&'a [Value], String> {
+    match value {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(value) => as_array(value, name),
+    }
+}
+
+/// Converts a list of OTLP key-values into an object.
+fn attributes(attributes: Option<&Value>) -> Map<String, Value> {
+    let mut object = Map::new();
+    for attribute in attributes.and_then(Value::as_array).into_iter().flatten() {
+        if let Some(key) = attribute.get("key").and_then(Value::as_str) {
+            let value = attribute.get("value").map_or(Value::Null, any_value);
+            object.insert(key.to_string(), value);
+        }
+    }
+    object
+}
+
+/// Converts an OTLP `AnyValue` into a plain JSON value.
+fn any_value(value: &Value) -> Value {
+    let Some(object) = value.as_object() else {
+        return Value::Null;
+    };
+    if let Some(value) = object.get("stringValue") {
+        value.clone()
+    } else if let Some(value) = object.get("boolValue") {
+        value.clone()
+    } else if let Some(value) = object.get("intValue") {
+        // 64-bit integers are encoded as strings in JSON
+        match value {
+            Value::String(s) => s.parse::<i64>().map_or_else(|_| value.clone(), Value::from),
+            value => value.clone(),
+        }
+    } else if let Some(value) = object.get("doubleValue") {
+        value.clone()
+    } else if let Some(value) = object.get("bytesValue") {
+        value.clone()
+    } else if let Some(array) = object.get("arrayValue") {
+        let values = array.get("values").and_then(Value::as_array);
+        Value::Array(values.into_iter().flatten().map(any_value).collect())
+    } else if let Some(kvlist) = object.get("kvlistValue") {
+        Value::Object(attributes(kvlist.get("values")))
+    } else {
+        Value::Null
+    }
+}
+
+/// Parses nanoseconds since the Unix epoch, encoded as a string or a number.
+fn nanos(value: &Value) -> Option<i128> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.as_u64().map(i128::from),
+        _ => None,
+    }
+}
+
+/// Converts nanoseconds since the Unix epoch into an RFC 3339 timestamp; zero means unknown.
+fn timestamp(value: Option<&Value>) -> Value {
+    match value.and_then(nanos) {
+        Some(0) | None => Value::Null,
+        Some(nanos) => match jiff::Timestamp::from_nanosecond(nanos) {
+            Ok(ts) => Value::String(ts.to_string()),
+            Err(_) => Value::Null,
+        },
+    }
+}
+
+/// Normalizes a trace or span ID, where an empty ID means absent.
+fn id(value: Option<&Value>) -> Value {
+    match value.and_then(Value::as_str) {
+        Some(id) if !id.is_empty() => Value::String(id.to_ascii_lowercase()),
+        _ => Value::Null,
+    }
+}
+
+fn span_kind(value: Option<&Value>) -> Value {
+    let kind = match value {
+        Some(Value::Number(n)) => match n.as_u64() {
+            Some(1) => "internal",
+            Some(2) => "server",
+            Some(3) => "client",
+            Some(4) => "producer",
+            Some(5) => "consumer",
+            _ => return Value::Null,
+        },
+        // the enum name, e.g., `SPAN_KIND_SERVER`
+        Some(Value::String(s)) => {
+            let name = s.strip_prefix("SPAN_KIND_").unwrap_or(s);
+            return Value::String(name.to_ascii_lowercase());
+        }
+        _ => return Value::Null,
+    };
+    Value::String(kind.to_string())
+}
+
+fn status_code(value: Option<&Value>) -> Value {
+    let code = match value {
+        None | Some(Value::Null) => "unset",
+        Some(Value::Number(n)) => match n.as_u64() {
+            Some(0) => "unset",
+            Some(1) => "ok",
+            Some(2) => "error",
+            _ => return Value::Null,
+        },
+        Some(Value::String(s)) => {
+            let name = s.strip_prefix("STATUS_CODE_").unwrap_or(s);
+            return Value::String(name.to_ascii_lowercase());
+        }
+        Some(_) => return Value::Null,
+    };
+    Value::String(code.to_string())
+}
+
+fn is_empty_string(value: &Value) -> bool {
+    value.as_str().is_some_and(str::is_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_flatten_logs() {
+        let request = json!({"resourceLogs": [{
+            "resource": {"attributes": [
+                {"key": "service.name", "value": {"stringValue": "api"}},
+                {"key": "host.cpus", "value": {"intValue": "8"}},
+            ]},
+            "scopeLogs": [{
+                "scope": {"name": "app", "version": "1.0"},
+                "logRecords": [{
+                    "timeUnixNano": "1767225600000000001",
+                    "observedTimeUnixNano": "1767225600500000000",
+                    "severityNumber": 9,
+                    "severityText": "INFO",
+                    "body": {"kvlistValue": {"values": [
+                        {"key": "msg", "value": {"stringValue": "hello"}},
+                        {"key": "tags", "value": {"arrayValue": {"values": [{"boolValue": true}]}}},
+                    ]}},
+                    "attributes": [{"key": "http.status", "value": {"intValue": 200}}],
+                    "traceId": "5B8EFFF798038103D269B633813FC60C",
+                    "spanId": "",
+                }],
+            }],
+        }]});
+        assert_eq!(
+            flatten_otlp(&request).unwrap(),
+            vec![json!({
+                "resource": {"service.name": "api", "host.cpus": 8},
+                "scope_name": "app",
+                "scope_version": "1.0",
+                "timestamp": "2026-01-01T00:00:00.000000001Z",
+                "observed_timestamp": "2026-01-01T00:00:00.5Z",
+                "severity_number": 9,
+                "severity_text": "INFO",
+                "body": {"msg": "hello", "tags": [true]},
+                "attributes": {"http.status": 200},
+                "trace_id": "5b8efff798038103d269b633813fc60c",
+                "span_id": null,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_flatten_spans() {
+        let request = json!({"resourceSpans": [{
+            "resource": {"attributes": []},
+            "scopeSpans": [{
+                "scope": {"name": "http"},
+                "spans": [{
+                    "traceId": "5b8efff798038103d269b633813fc60c",
+                    "spanId": "eee19b7ec3c1b174",
+                    "parentSpanId": "",
+                    "name": "GET /",
+                    "kind": 2,
+                    "startTimeUnixNano": "1767225600000000000",
+                    "endTimeUnixNano": "1767225600250000000",
+                    "status": {"code": 2, "message": "boom"},
+                    "events": [{"timeUnixNano": "1767225600100000000", "name": "retry"}],
+                }],
+            }],
+        }]});
+        assert_eq!(
+            flatten_otlp(&request).unwrap(),
+            vec![json!({
+                "resource": {},
+                "scope_name": "http",
+                "scope_version": null,
+                "trace_id": "5b8efff798038103d269b633813fc60c",
+                "span_id": "eee19b7ec3c1b174",
+                "parent_span_id": null,
+                "name": "GET /",
+                "kind": "server",
+                "start_timestamp": "2026-01-01T00:00:00Z",
+                "end_timestamp": "2026-01-01T00:00:00.25Z",
+                "duration_nanos": 250000000,
+                "attributes": {},
+                "status_code": "error",
+                "status_message": "boom",
+                "events": [{"timestamp": "2026-01-01T00:00:00.1Z", "name": "retry", "attributes": {}}],
+                "links": [],
+            })]
+        );
+
+        assert!(flatten_otlp(&json!({"resourceMetrics": []})).is_err());
+        assert!(flatten_otlp(&json!({"resourceSpans": {}})).is_err());
+    }
+
+    #[test]
+    fn test_read_otlp_rows() {
+        let data = concat!(
+            r#"{"resourceLogs":[{"scopeLogs":[{"logRecords":[{"body":{"stringValue":"a"}},{"body":{"stringValue":"b"}}]}]}]}"#,
+            "\n",
+            r#"{"unknown":1}"#,
+            "\n",
+        );
+        let rows = read_otlp_rows(data.as_bytes(), JsonLayout::Lines)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert!(matches!(&rows[0], Row::Valid(row) if row["body"] == "a"));
+        assert!(matches!(&rows[1], Row::Valid(row) if row["body"] == "b"));
+        assert!(matches!(&rows[2], Row::Rejected(_)));
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use exn::Result;
+use exn::bail;
+use indicatif::ProgressBar;
+use scopeql_parser::TokenKind;
+use serde_json::Value;
+
+use crate::Error;
+use crate::client::ScopeQLClient;
+use crate::client::StatementOutput;
+use crate::load::DataFormat;
+use crate::load::FormatOptions;
+use crate::load::Row;
+use crate::load::RowReader;
+use crate::load::display_name;
+use crate::load::open_rows;
+use crate::load::reject::ErrorOptions;
+use crate::tokenizer::run_tokenizer;
+
+/// Runs the transform over the first `limit` rows of the file without inserting them, and
+/// returns the output of the statement.
+///
+/// The rows are sent as a `VALUES` clause, followed by the transform without its `INSERT INTO`
+/// clause, so that nothing is written. Malformed rows are skipped up to `--max-errors` as in
+/// the load, but not written to the reject file.
+pub async fn preview(
+    client: &ScopeQLClient,
+    file: &Path,
+    format: DataFormat,
+    transform: &str,
+    format_options: &FormatOptions,
+    error_options: &ErrorOptions,
+    limit: usize,
+) -> Result<StatementOutput, Error> {
+    let query = strip_insert(transform)?;
+
+    let rows = open_rows(file, format, format_options.clone(), ProgressBar::hidden())?;
+    let values = read_preview_rows(file, rows, error_options, limit)?;
+    if values.is_empty() {
+        bail!(Error::new(format!(
+            "no rows to preview in {}",
+            display_name(file)
+        )));
+    }
+
+    let statement = make_preview_statement(&values, query);
+    log::debug!("preview statement: {statement}");
+    client.preview_statement(statement).await
+}
+
+/// Reads up to `limit` valid rows, skipping malformed rows up to `--max-errors`.
+fn read_preview_rows(
+    file: &Path,
+    rows: RowReader,
+    error_options: &ErrorOptions,
+    limit: usize,
+) -> Result<Vec<Value>, Error> {
+    let mut values = vec![];
+    let mut num_rows_rejected = 0;
+    for row in rows {
+        if values.len() >= limit {
+            break;
+        }
+        match row? {
+            Row::Valid(value) => values.push(value),
+            Row::Rejected(row) => {
+                num_rows_rejected += 1;
+                error_options.check_max_errors(&row, num_rows_rejected)?;
+                log::warn!("{}: skipped a malformed row ({row})", display_name(file));
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Validates the tokens of the transform, and returns the transform without its top-level
+/// `INSERT INTO` clause.
+fn strip_insert(transform: &str) -> Result<&str, Error> {
+    let tokens = run_tokenizer(transform)?;
+
+    let mut depth = 0usize;
+    let mut significant = tokens
+        .iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment));
+    while let Some(token) = significant.next() {
+        match token.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => bail!(Error::new(format!(
+                    "unbalanced `)` at position {} of the transform",
+                    token.span.start
+                ))),
+            },
+            TokenKind::INSERT if depth == 0 => {
+                return match significant.next().map(|token| token.kind) {
+                    Some(TokenKind::INTO) => Ok(transform[..token.span.start].trim_end()),
+                    _ => bail!(Error::new(format!(
+                        "expected `INTO` after `INSERT` at position {} of the transform",
+                        token.span.start
+                    ))),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    if depth > 0 {
+        bail!(Error::new("unclosed `(` in the transform".to_string()));
+    }
+    bail!(Error::new(
+        "the transform does not insert into any table; expected `INSERT INTO <table>`".to_string()
+    ))
+}
+
+/// Makes a statement that applies the query to the rows given as a `VALUES` clause, where each
+/// row is the single column `$0`, as in ingestion.
+fn make_preview_statement(rows: &[Value], query: &str) -> String {
+    let values = rows
+        .iter()
+        .map(|row| format!("(PARSE_JSON({}))", quote_string(&row.to_string())))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    if query.is_empty() {
+        format!("VALUES {values}")
+    } else {
+        format!("VALUES {values}\n{query}")
+    }
+}
+
+/// Quotes the string as a single-quoted string literal.
+pub fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for c in s.chars() {
+        if c == '\'' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::load::reject::RejectedRow;
+
+    #[test]
+    fn test_strip_insert() {
+        assert_eq!(
+            strip_insert("SELECT $0['a'] AS a INSERT INTO t").unwrap(),
+            "SELECT $0['a'] AS a"
+        );
+        assert_eq!(
+            strip_insert("where (x = 'insert into') -- note\n insert\ninto db.t").unwrap(),
+            "where (x = 'insert into') -- note"
+        );
+        assert_eq!(strip_insert("INSERT INTO t").unwrap(), "");
+        assert!(strip_insert("SELECT $0").is_err());
+        assert!(strip_insert("SELECT (1 INSERT INTO t").is_err());
+        assert!(strip_insert("INSERT t").is_err());
+    }
+
+    #[test]
+    fn test_read_preview_rows() {
+        let rows = || -> RowReader {
+            let rejected = RejectedRow {
+                line: Some(2),
+                reason: "malformed".to_string(),
+                raw: None,
+            };
+            Box::new(
+                [
+                    Row::Valid(json!(1)),
+                    Row::Rejected(rejected),
+                    Row::Valid(json!(2)),
+                ]
+                .into_iter()
+                .map(Ok),
+            )
+        };
+        let file = Path::new("data.json");
+        let mut error_options = ErrorOptions {
+            max_errors: 0,
+            reject_file: None,
+        };
+        assert!(read_preview_rows(file, rows(), &error_options, 10).is_err());
+        assert_eq!(
+            read_preview_rows(file, rows(), &error_options, 1).unwrap(),
+            vec![json!(1)]
+        );
+        error_options.max_errors = 1;
+        assert_eq!(
+            read_preview_rows(file, rows(), &error_options, 10).unwrap(),
+            vec![json!(1), json!(2)]
+        );
+    }
+
+    #[test]
+    fn test_make_preview_statement() {
+        let rows = [json!({"a": "it's \"x\""}), json!(1)];
+        assert_eq!(
+            make_preview_statement(&rows, "SELECT $0"),
+            "VALUES (PARSE_JSON('{\"a\":\"it\\'s \\\\\"x\\\\\"\"}')),\n(PARSE_JSON('1'))\nSELECT $0"
+        );
+    }
+}
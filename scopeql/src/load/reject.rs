@@ -0,0 +1,132 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use serde_json::json;
+
+use crate::Error;
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Error Options")]
+pub struct ErrorOptions {
+    /// Maximum number of malformed rows skipped in each file before the load fails; `0` fails
+    /// at the first malformed row.
+    ///
+    /// Malformed rows are CSV records, JSON lines and log lines that cannot be parsed. Errors
+    /// that leave the rest of the file unreadable, e.g., an invalid JSON array, always fail the
+    /// load. With `--follow`, malformed lines are always skipped.
+    #[clap(long, value_name = "N", default_value = "0")]
+    pub max_errors: u64,
+    /// Write the malformed rows to the given file as JSON lines, with the data file, the line
+    /// number, the reason and the raw content of each row.
+    #[clap(long, value_name = "FILE")]
+    pub reject_file: Option<PathBuf>,
+}
+
+impl ErrorOptions {
+    /// Fails if `num_rows_rejected`, the number of malformed rows of a file up to `row`, exceeds
+    /// `--max-errors`.
+    pub fn check_max_errors(&self, row: &RejectedRow, num_rows_rejected: u64) -> Result<(), Error> {
+        if num_rows_rejected > self.max_errors {
+            bail!(Error::new(match self.max_errors {
+                0 => format!("malformed row ({row}); use --max-errors to skip malformed rows"),
+                n => format!("more than {n} malformed rows; the last is ({row})"),
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// A malformed row that cannot be read from a data source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRow {
+    /// The 1-based line number where the row starts, if known.
+    pub line: Option<u64>,
+    pub reason: String,
+    /// The raw content of the row, if available.
+    pub raw: Option<String>,
+}
+
+impl fmt::Display for RejectedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// A file where rejected rows are written as JSON lines, shared by the files of a load.
+pub struct RejectFile {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl RejectFile {
+    /// Creates the reject file, or appends to it if `append` is true, e.g., for a resumed load.
+    pub fn open(path: PathBuf, append: bool) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .or_raise(|| Error::new(format!("failed to open reject file: {}", path.display())))?;
+        Ok(RejectFile {
+            path,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, file: &str, row: &RejectedRow) -> Result<(), Error> {
+        let record = json!({
+            "file": file,
+            "line": row.line,
+            "reason": row.reason,
+            "raw": row.raw,
+        });
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{record}").or_raise(|| {
+            Error::new(format!(
+                "failed to write reject file: {}",
+                self.path.display()
+            ))
+        })
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush().or_raise(|| {
+            Error::new(format!(
+                "failed to write reject file: {}",
+                self.path.display()
+            ))
+        })
+    }
+}
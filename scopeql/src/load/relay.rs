@@ -0,0 +1,960 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use exn::Result;
+use exn::ResultExt;
+use http_body_util::BodyExt;
+use http_body_util::Full;
+use http_body_util::Limited;
+use hyper::Method;
+use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
+use hyper::body::Bytes;
+use hyper::body::Incoming;
+use hyper::header;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::Error;
+use crate::client::Response as IngestResponse;
+use crate::client::ScopeQLClient;
+use crate::config::Config;
+use crate::global;
+use crate::load::CsvOptions;
+use crate::load::IngestMode;
+use crate::load::Row;
+use crate::load::RowReader;
+use crate::load::compress::Compression;
+use crate::load::compress::decompress_with;
+use crate::load::csv::CsvReader;
+use crate::load::json::JsonLayout;
+use crate::load::json::JsonOptions;
+use crate::load::json::read_json_rows;
+use crate::load::otlp::read_otlp_rows;
+use crate::load::parse_duration;
+use crate::load::parse_size;
+use crate::load::spool::Spool;
+
+/// How often spooled batches are retried.
+const REPLAY_INTERVAL: Duration = Duration::from_secs(5);
+/// The maximum delay between retries of a batch that can be neither sent nor spooled.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Relay Options")]
+pub struct RelayOptions {
+    /// The address to listen on.
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:6580")]
+    pub listen: SocketAddr,
+    /// How ingested rows are committed on the server.
+    #[clap(long, value_enum, value_name = "MODE", default_value = "committed")]
+    pub mode: IngestMode,
+    /// Maximum number of rows in a batch.
+    #[clap(long, value_name = "ROWS", default_value = "100000")]
+    pub batch_rows: usize,
+    /// Maximum size of a batch, e.g., `16MiB`.
+    #[clap(long, value_name = "SIZE", default_value = "16MiB", value_parser = parse_size)]
+    pub batch_size: usize,
+    /// Maximum time a received row waits before its batch is sent, e.g., `500ms` or `5s`.
+    #[clap(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    pub flush_interval: Duration,
+    /// Maximum size of a request body after decompression.
+    #[clap(long, value_name = "SIZE", default_value = "16MiB", value_parser = parse_size)]
+    pub max_body_size: usize,
+    /// Maximum size of the rows received but not yet sent or spooled; requests are refused
+    /// with `503 Service Unavailable` while the buffer is full.
+    #[clap(long, value_name = "SIZE", default_value = "64MiB", value_parser = parse_size)]
+    pub max_pending_size: usize,
+    /// Maximum time a request waits for room in the buffer before it is refused, e.g., `5s`.
+    #[clap(long, value_name = "DURATION", default_value = "5s", value_parser = parse_duration)]
+    pub enqueue_timeout: Duration,
+    /// Write batches to files in the given directory while ScopeDB is unreachable, and send
+    /// them in order once it is back.
+    ///
+    /// Batches left in the directory are sent when the relay restarts. Without a spool, a
+    /// failed batch is retried in memory, and requests are refused once the buffer is full;
+    /// pressing Ctrl-C twice exits without waiting for the buffered rows to be ingested.
+    #[clap(long, value_name = "DIR")]
+    pub spool_dir: Option<PathBuf>,
+    /// Maximum total size of the spooled batches.
+    #[clap(long, value_name = "SIZE", default_value = "1GiB", value_parser = parse_size, requires = "spool_dir")]
+    pub spool_size: usize,
+}
+
+/// Rows parsed from a request, with the buffer space they hold until they are sent or spooled.
+struct Chunk {
+    data: String,
+    rows: usize,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// The state shared by the request handlers.
+struct RelayState {
+    sender: mpsc::UnboundedSender<Chunk>,
+    pending: Arc<Semaphore>,
+    options: RelayOptions,
+    csv_options: CsvOptions,
+}
+
+/// Runs the ingest relay until interrupted.
+pub fn serve_ingest(
+    config: &Config,
+    transform: String,
+    options: RelayOptions,
+    csv_options: CsvOptions,
+) {
+    let connection = config
+        .get_default_connection()
+        .expect("no default connection in config");
+    let client = ScopeQLClient::new(connection.endpoint().to_owned())
+        .with_compression(connection.compression());
+
+    if let Err(err) = global::rt().block_on(run(client, transform, options, csv_options)) {
+        log::error!("{err:?}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(
+    client: ScopeQLClient,
+    transform: String,
+    options: RelayOptions,
+    csv_options: CsvOptions,
+) -> Result<(), Error> {
+    let spool = match &options.spool_dir {
+        Some(dir) => {
+            let spool = Spool::open(dir.clone(), options.spool_size as u64)?;
+            if !spool.is_empty() {
+                log::info!(
+                    "{} spooled batch(es) found in {}",
+                    spool.len(),
+                    dir.display()
+                );
+            }
+            Some(spool)
+        }
+        None => None,
+    };
+
+    let listener = TcpListener::bind(options.listen)
+        .await
+        .or_raise(|| Error::new(format!("failed to listen on {}", options.listen)))?;
+    log::info!(
+        "accepting rows at http://{}/ingest; press Ctrl-C to stop",
+        options.listen
+    );
+
+    let batcher = Batcher {
+        client,
+        transform,
+        options: options.clone(),
+        spool,
+    };
+    let interrupted = || async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    serve(listener, batcher, options, csv_options, interrupted).await
+}
+
+/// Accepts requests until interrupted, and then waits for the batcher to ingest or spool the
+/// buffered rows, unless interrupted again.
+async fn serve<F: Future<Output = ()>>(
+    listener: TcpListener,
+    batcher: Batcher,
+    options: RelayOptions,
+    csv_options: CsvOptions,
+    interrupted: impl Fn() -> F,
+) -> Result<(), Error> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let pending = Arc::new(Semaphore::new(options.max_pending_size));
+    let (shutdown, shutdown_receiver) = oneshot::channel();
+    let mut batcher = tokio::spawn(batcher.run(receiver, shutdown_receiver));
+
+    let state = Arc::new(RelayState {
+        sender,
+        pending,
+        options,
+        csv_options,
+    });
+    // created once so that an interrupt received while accepting a connection is not lost
+    let first_interrupt = interrupted();
+    tokio::pin!(first_interrupt);
+    loop {
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    log::warn!("failed to accept connection: {err}");
+                    continue;
+                }
+            },
+            _ = &mut first_interrupt => break,
+        };
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|request| handle(state.clone(), request));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                log::debug!("failed to serve connection: {err}");
+            }
+        });
+    }
+
+    drop(listener);
+    log::info!("interrupted; ingesting buffered rows; press Ctrl-C again to drop them and exit");
+    let _ = shutdown.send(());
+    tokio::select! {
+        joined = &mut batcher => {
+            joined.or_raise(|| Error::new("failed to join the batcher".to_string()))
+        }
+        _ = interrupted() => {
+            batcher.abort();
+            let size = state.options.max_pending_size - state.pending.available_permits();
+            log::warn!("interrupted again; dropped {size} bytes of buffered rows");
+            Ok(())
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<RelayState>,
+    request: Request<Incoming>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => respond(StatusCode::OK, json!({"status": "ok"})),
+        (&Method::POST, "/ingest") => match ingest(&state, request, false).await {
+            Ok(rows) => respond(StatusCode::ACCEPTED, json!({"accepted_rows": rows})),
+            Err((status, message)) => respond_error(status, message),
+        },
+        // OTLP/HTTP exporters expect an empty export response once the request is accepted
+        (&Method::POST, "/v1/logs" | "/v1/traces") => match ingest(&state, request, true).await {
+            Ok(_) => respond(StatusCode::OK, json!({})),
+            Err((status, message)) => respond_error(status, message),
+        },
+        (_, "/health" | "/ingest" | "/v1/logs" | "/v1/traces") => respond(
+            StatusCode::METHOD_NOT_ALLOWED,
+            json!({"error": "method not allowed"}),
+        ),
+        _ => respond(StatusCode::NOT_FOUND, json!({"error": "not found"})),
+    };
+    Ok(response)
+}
+
+fn respond(status: StatusCode, body: serde_json::Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn respond_error(status: StatusCode, message: String) -> Response<Full<Bytes>> {
+    let mut response = respond(status, json!({"error": message}));
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        let retry_after = header::HeaderValue::from_static("1");
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after);
+    }
+    response
+}
+
+/// Parses the rows of the request and queues them for ingestion, and returns the number of
+/// rows accepted. Either all rows of a request are accepted, or none.
+///
+/// An OTLP request is an OTLP/HTTP logs or traces export encoded as JSON.
+async fn ingest(
+    state: &RelayState,
+    request: Request<Incoming>,
+    otlp: bool,
+) -> std::result::Result<usize, (StatusCode, String)> {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or_default().trim())
+        .unwrap_or("application/x-ndjson")
+        .to_ascii_lowercase();
+    let format = match otlp {
+        true if content_type == "application/json" => Some(RequestFormat::Otlp),
+        true => None,
+        false => RequestFormat::from_content_type(&content_type),
+    };
+    let format = format.ok_or_else(|| {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("unsupported content type: {content_type}"),
+        )
+    })?;
+    let compression = match request.headers().get(header::CONTENT_ENCODING) {
+        None => None,
+        Some(value) => match value.to_str().unwrap_or_default() {
+            "identity" => None,
+            encoding => Some(Compression::from_content_encoding(encoding).ok_or_else(|| {
+                (
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("unsupported content encoding: {encoding}"),
+                )
+            })?),
+        },
+    };
+
+    let max_body_size = state.options.max_body_size;
+    let too_large = || {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request body exceeds {max_body_size} bytes"),
+        )
+    };
+    let body = Limited::new(request.into_body(), max_body_size)
+        .collect()
+        .await
+        .map_err(|_| too_large())?
+        .to_bytes();
+    let mut reader = decompress_with(Path::new("request body"), Cursor::new(body), compression)
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("{err}")))?
+        .take(max_body_size as u64 + 1);
+    let mut body = vec![];
+    reader.read_to_end(&mut body).map_err(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("failed to read body: {err}"),
+        )
+    })?;
+    if body.len() > max_body_size {
+        return Err(too_large());
+    }
+
+    let (data, rows) = parse_rows(format, body, &state.csv_options)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    if rows == 0 {
+        return Ok(0);
+    }
+
+    let size = u32::try_from(data.len())
+        .ok()
+        .filter(|size| *size as usize <= state.options.max_pending_size)
+        .ok_or_else(too_large)?;
+    let permit = tokio::time::timeout(
+        state.options.enqueue_timeout,
+        state.pending.clone().acquire_many_owned(size),
+    )
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the relay is busy; retry later".to_string(),
+        )
+    })?
+    .map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the relay is shutting down".to_string(),
+        )
+    })?;
+
+    let chunk = Chunk {
+        data,
+        rows,
+        _permit: permit,
+    };
+    state.sender.send(chunk).map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the relay is shutting down".to_string(),
+        )
+    })?;
+    Ok(rows)
+}
+
+/// A data format of request bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestFormat {
+    /// A JSON array or a stream of JSON values.
+    Json,
+    /// One JSON value per line.
+    Ndjson,
+    Csv,
+    /// An OTLP logs or traces export encoded as JSON.
+    Otlp,
+}
+
+impl RequestFormat {
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "application/json" => Some(RequestFormat::Json),
+            "application/x-ndjson"
+            | "application/ndjson"
+            | "application/jsonl"
+            | "application/x-jsonlines" => Some(RequestFormat::Ndjson),
+            "text/csv" => Some(RequestFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the body into JSON lines, and returns the lines and the number of rows; fails on the
+/// first malformed row.
+fn parse_rows(
+    format: RequestFormat,
+    body: Vec<u8>,
+    csv_options: &CsvOptions,
+) -> std::result::Result<(String, usize), String> {
+    let reader = Cursor::new(body);
+    let rows: RowReader = match format {
+        RequestFormat::Json | RequestFormat::Ndjson => {
+            let layout = match format {
+                RequestFormat::Ndjson => JsonLayout::Lines,
+                _ => JsonLayout::Auto,
+            };
+            read_json_rows(reader, layout, JsonOptions { json_path: None })
+                .map_err(|err| format!("{err}"))?
+        }
+        RequestFormat::Csv => {
+            Box::new(CsvReader::new(reader, csv_options.clone()).map_err(|err| format!("{err}"))?)
+        }
+        RequestFormat::Otlp => {
+            read_otlp_rows(reader, JsonLayout::Auto).map_err(|err| format!("{err}"))?
+        }
+    };
+
+    let mut data = String::new();
+    let mut num_rows = 0;
+    for row in rows {
+        match row.map_err(|err| format!("{err}"))? {
+            Row::Valid(row) => {
+                writeln!(&mut data, "{row}").unwrap();
+                num_rows += 1;
+            }
+            Row::Rejected(row) => return Err(format!("malformed row ({row})")),
+        }
+    }
+    Ok((data, num_rows))
+}
+
+/// Collects the received rows into batches, and sends them to ScopeDB or the spool.
+struct Batcher {
+    client: ScopeQLClient,
+    transform: String,
+    options: RelayOptions,
+    spool: Option<Spool>,
+}
+
+impl Batcher {
+    /// Runs until shut down, and then ingests the rows received before.
+    async fn run(
+        mut self,
+        mut receiver: mpsc::UnboundedReceiver<Chunk>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) {
+        let mut closed = false;
+        let mut batch: Vec<Chunk> = vec![];
+        let mut batch_rows = 0;
+        let mut batch_size = 0;
+        let mut deadline = None;
+        let mut replay = tokio::time::interval(REPLAY_INTERVAL);
+
+        loop {
+            let flush_at = deadline.unwrap_or_else(|| Instant::now() + REPLAY_INTERVAL);
+            let has_spooled = self.spool.as_ref().is_some_and(|spool| !spool.is_empty());
+            let chunk = tokio::select! {
+                chunk = receiver.recv() => match chunk {
+                    Some(chunk) => Some(chunk),
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(flush_at), if deadline.is_some() => None,
+                _ = replay.tick(), if has_spooled && !closed => {
+                    self.replay().await;
+                    continue;
+                }
+                _ = &mut shutdown, if !closed => {
+                    // refuse new rows, and receive the queued ones until the channel is empty
+                    receiver.close();
+                    closed = true;
+                    continue;
+                }
+            };
+
+            if let Some(chunk) = chunk {
+                batch_rows += chunk.rows;
+                batch_size += chunk.data.len();
+                batch.push(chunk);
+                deadline.get_or_insert_with(|| Instant::now() + self.options.flush_interval);
+                if batch_rows < self.options.batch_rows && batch_size < self.options.batch_size {
+                    continue;
+                }
+            }
+
+            self.flush(std::mem::take(&mut batch), batch_rows).await;
+            batch_rows = 0;
+            batch_size = 0;
+            deadline = None;
+        }
+
+        self.flush(batch, batch_rows).await;
+        if let Some(spool) = &self.spool
+            && !spool.is_empty()
+        {
+            log::warn!(
+                "{} batch(es) remain spooled and will be sent when the relay restarts",
+                spool.len()
+            );
+        }
+    }
+
+    /// Sends the batch, or writes it to the spool if ScopeDB is unreachable or earlier batches
+    /// are spooled, so that batches are ingested in order.
+    ///
+    /// A batch that can be neither sent nor spooled is retried until it succeeds, or the relay
+    /// is interrupted again while shutting down; meanwhile, the pending buffer fills up and
+    /// further requests are refused. A batch the server rejects is dropped, since sending it
+    /// again would fail the same way.
+    async fn flush(&mut self, batch: Vec<Chunk>, rows: usize) {
+        if batch.is_empty() {
+            return;
+        }
+        let data = batch
+            .iter()
+            .map(|chunk| chunk.data.as_str())
+            .collect::<String>();
+
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let has_spooled = self.spool.as_ref().is_some_and(|spool| !spool.is_empty());
+            if !has_spooled {
+                match self.send(data.clone()).await {
+                    Ok(n) => {
+                        log::debug!("ingested a batch of {rows} rows; {n} rows inserted");
+                        return;
+                    }
+                    Err(SendError::Rejected(err)) => {
+                        log::error!("dropped a batch of {rows} rows rejected by ScopeDB: {err}");
+                        return;
+                    }
+                    Err(SendError::Unavailable(err)) => {
+                        log::warn!("failed to ingest a batch of {rows} rows: {err}")
+                    }
+                }
+            }
+
+            if let Some(spool) = &mut self.spool {
+                match spool.push(&data) {
+                    Ok(true) => {
+                        log::info!("spooled a batch of {rows} rows");
+                        return;
+                    }
+                    Ok(false) if spool.is_empty() => {
+                        log::warn!("the batch of {rows} rows is larger than the spool")
+                    }
+                    Ok(false) => {
+                        log::warn!("the spool is full; sending spooled batches before new ones");
+                        if self.replay().await {
+                            continue;
+                        }
+                    }
+                    Err(err) => log::error!("{err:?}"),
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    /// Sends the spooled batches in order until one fails, and returns whether the spool has
+    /// been drained; a spooled batch the server rejects is dropped.
+    async fn replay(&mut self) -> bool {
+        loop {
+            let Some(spool) = &self.spool else {
+                return true;
+            };
+            let data = match spool.front() {
+                Ok(Some(data)) => data,
+                Ok(None) => return true,
+                Err(err) => {
+                    log::error!("{err:?}");
+                    return false;
+                }
+            };
+            match self.send(data).await {
+                Ok(_) => {}
+                Err(SendError::Rejected(err)) => {
+                    log::error!("dropped a spooled batch rejected by ScopeDB: {err}");
+                }
+                Err(SendError::Unavailable(err)) => {
+                    log::warn!("failed to ingest a spooled batch: {err}");
+                    return false;
+                }
+            }
+
+            let spool = self.spool.as_mut().unwrap();
+            if let Err(err) = spool.pop_front() {
+                log::error!("{err:?}");
+                return false;
+            }
+            if spool.is_empty() {
+                log::info!("all spooled batches have been ingested");
+            }
+        }
+    }
+
+    async fn send(&self, data: String) -> std::result::Result<u64, SendError> {
+        let ty = self.options.mode.into();
+        let response = self
+            .client
+            .try_load_jsonlines(data, self.transform.clone(), ty)
+            .await
+            .map_err(|err| SendError::Unavailable(format!("{err}")))?;
+        match response {
+            IngestResponse::Success(result) => Ok(result.num_rows_inserted as u64),
+            IngestResponse::Failed(err)
+                if err.code().is_server_error() || err.code() == StatusCode::TOO_MANY_REQUESTS =>
+            {
+                Err(SendError::Unavailable(err.to_string()))
+            }
+            IngestResponse::Failed(err) => Err(SendError::Rejected(err.to_string())),
+        }
+    }
+}
+
+/// Why a batch was not ingested.
+enum SendError {
+    /// ScopeDB is unreachable, or temporarily unable to ingest; the batch may be sent again.
+    Unavailable(String),
+    /// ScopeDB refuses the batch, e.g., for a bad transform or a row it cannot ingest.
+    Rejected(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Notify;
+
+    use super::*;
+    use crate::client::mock::MockServer;
+
+    fn relay_options() -> RelayOptions {
+        RelayOptions {
+            listen: "127.0.0.1:0".parse().unwrap(),
+            mode: IngestMode::Committed,
+            batch_rows: 100000,
+            batch_size: 16 << 20,
+            flush_interval: Duration::from_secs(60),
+            max_body_size: 16 << 20,
+            max_pending_size: 64 << 20,
+            enqueue_timeout: Duration::from_secs(5),
+            spool_dir: None,
+            spool_size: 1 << 30,
+        }
+    }
+
+    fn batcher(server: &MockServer, options: &RelayOptions, spool: Option<Spool>) -> Batcher {
+        Batcher {
+            client: ScopeQLClient::new(server.endpoint().to_string()),
+            transform: "INSERT INTO t".to_string(),
+            options: options.clone(),
+            spool,
+        }
+    }
+
+    fn chunk(data: &str) -> Chunk {
+        let pending = Arc::new(Semaphore::new(data.len()));
+        Chunk {
+            data: data.to_string(),
+            rows: data.lines().count(),
+            _permit: pending.try_acquire_many_owned(data.len() as u32).unwrap(),
+        }
+    }
+
+    /// Starts the relay, which is interrupted each time `interrupt` is notified.
+    async fn start_relay(
+        batcher: Batcher,
+        options: RelayOptions,
+        interrupt: Arc<Notify>,
+    ) -> (String, tokio::task::JoinHandle<Result<(), Error>>) {
+        let listener = TcpListener::bind(options.listen).await.unwrap();
+        let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+        let interrupted = move || {
+            let interrupt = interrupt.clone();
+            async move { interrupt.notified().await }
+        };
+        let relay = tokio::spawn(serve(
+            listener,
+            batcher,
+            options,
+            CsvOptions::default(),
+            interrupted,
+        ));
+        (url, relay)
+    }
+
+    async fn post(url: &str, body: &str) -> reqwest::StatusCode {
+        let headers = [(header::CONTENT_TYPE, "application/x-ndjson")];
+        post_with_headers(url, &headers, body.as_bytes().to_vec()).await
+    }
+
+    async fn post_with_headers(
+        url: &str,
+        headers: &[(header::HeaderName, &str)],
+        body: Vec<u8>,
+    ) -> reqwest::StatusCode {
+        let client = reqwest::ClientBuilder::new().no_proxy().build().unwrap();
+        let mut request = client.post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        request.send().await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_batcher_spool() {
+        let dir = std::env::temp_dir().join(format!("scopeql-relay-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let server = MockServer::start().await;
+        let spool = Spool::open(dir.clone(), 1024).unwrap();
+        let mut batcher = batcher(&server, &relay_options(), Some(spool));
+
+        // a failed batch is spooled, and later batches are spooled behind it without being sent
+        server.set_unavailable(true);
+        batcher.flush(vec![chunk("{\"a\":1}\n")], 1).await;
+        batcher
+            .flush(vec![chunk("{\"a\":2}\n"), chunk("{\"a\":3}\n")], 2)
+            .await;
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(batcher.spool.as_ref().unwrap().len(), 2);
+        assert!(!batcher.replay().await);
+        assert_eq!(batcher.spool.as_ref().unwrap().len(), 2);
+
+        // spooled batches are replayed in order once ScopeDB is back
+        server.set_unavailable(false);
+        assert!(batcher.replay().await);
+        batcher.flush(vec![chunk("{\"a\":4}\n")], 1).await;
+        let rows = server.requests()[2..]
+            .iter()
+            .map(|request| request.rows())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec!["{\"a\":1}\n", "{\"a\":2}\n{\"a\":3}\n", "{\"a\":4}\n"]
+        );
+        assert!(batcher.spool.as_ref().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batcher_rejected() {
+        let dir =
+            std::env::temp_dir().join(format!("scopeql-relay-rejected-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let server = MockServer::start().await;
+        let spool = Spool::open(dir.clone(), 1024).unwrap();
+        let mut batcher = batcher(&server, &relay_options(), Some(spool));
+        let rejected = r#"{"message":"bad transform"}"#;
+
+        // a rejected batch is dropped rather than spooled
+        server.respond(400, &[], rejected);
+        batcher.flush(vec![chunk("{\"a\":1}\n")], 1).await;
+        assert!(batcher.spool.as_ref().unwrap().is_empty());
+
+        // a rejected spooled batch is dropped, and does not hold back the batches behind it
+        server.set_unavailable(true);
+        batcher.flush(vec![chunk("{\"a\":2}\n")], 1).await;
+        batcher.flush(vec![chunk("{\"a\":3}\n")], 1).await;
+        assert_eq!(batcher.spool.as_ref().unwrap().len(), 2);
+        server.set_unavailable(false);
+        server.respond(422, &[], rejected);
+        assert!(batcher.replay().await);
+        assert!(batcher.spool.as_ref().unwrap().is_empty());
+
+        let rows = server
+            .requests()
+            .iter()
+            .map(|request| request.rows())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec!["{\"a\":1}\n", "{\"a\":2}\n", "{\"a\":2}\n", "{\"a\":3}\n"]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_drain_on_shutdown() {
+        let server = MockServer::start().await;
+        let options = relay_options();
+        let interrupt = Arc::new(Notify::new());
+        let batcher = batcher(&server, &options, None);
+        let (url, relay) = start_relay(batcher, options, interrupt.clone()).await;
+
+        assert_eq!(post(&url, "{\"a\":1}\n").await, StatusCode::ACCEPTED);
+        assert_eq!(post(&url, "{\"a\":2}\n").await, StatusCode::ACCEPTED);
+        assert!(server.requests().is_empty());
+
+        // the buffered rows are ingested in a single batch on shutdown
+        interrupt.notify_one();
+        relay.await.unwrap().unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].rows(), "{\"a\":1}\n{\"a\":2}\n");
+    }
+
+    #[tokio::test]
+    async fn test_serve_backpressure() {
+        let server = MockServer::start().await;
+        server.set_unavailable(true);
+        let options = RelayOptions {
+            flush_interval: Duration::from_millis(10),
+            max_pending_size: 12,
+            enqueue_timeout: Duration::from_millis(100),
+            ..relay_options()
+        };
+        let interrupt = Arc::new(Notify::new());
+        let batcher = batcher(&server, &options, None);
+        let (url, relay) = start_relay(batcher, options, interrupt.clone()).await;
+
+        // the failed batch is retried in memory, and holds the pending buffer
+        assert_eq!(post(&url, "{\"a\":1}\n").await, StatusCode::ACCEPTED);
+        assert_eq!(
+            post(&url, "{\"a\":2}\n").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            post(&url, "{\"a\":1234567}\n").await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // the shutdown waits for the failed batch until interrupted again
+        interrupt.notify_one();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!relay.is_finished());
+        interrupt.notify_one();
+        relay.await.unwrap().unwrap();
+        assert!(!server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serve_content_encoding() {
+        use std::io::Write;
+
+        let server = MockServer::start().await;
+        let options = relay_options();
+        let interrupt = Arc::new(Notify::new());
+        let batcher = batcher(&server, &options, None);
+        let (url, relay) = start_relay(batcher, options, interrupt.clone()).await;
+
+        // a body is not decompressed without a Content-Encoding, even if it looks compressed
+        let headers = [(header::CONTENT_TYPE, "text/csv")];
+        let body = b"BZh\n1\n".to_vec();
+        assert_eq!(
+            post_with_headers(&url, &headers, body).await,
+            StatusCode::ACCEPTED
+        );
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(b"{\"a\":1}\n").unwrap();
+        let body = encoder.finish().unwrap();
+        let headers = [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_ENCODING, "gzip"),
+        ];
+        assert_eq!(
+            post_with_headers(&url, &headers, body).await,
+            StatusCode::ACCEPTED
+        );
+
+        let headers = [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_ENCODING, "br"),
+        ];
+        let body = b"{\"a\":1}\n".to_vec();
+        assert_eq!(
+            post_with_headers(&url, &headers, body).await,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        interrupt.notify_one();
+        relay.await.unwrap().unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].rows(), "{\"BZh\":\"1\"}\n{\"a\":1}\n");
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let csv_options = CsvOptions::default();
+
+        let body = b"{\"a\":1}\n{\"a\":2}\n".to_vec();
+        assert_eq!(
+            parse_rows(RequestFormat::Ndjson, body, &csv_options),
+            Ok(("{\"a\":1}\n{\"a\":2}\n".to_string(), 2))
+        );
+        let body = b"[{\"a\":1}, {\"a\":2}]".to_vec();
+        assert_eq!(
+            parse_rows(RequestFormat::Json, body, &csv_options),
+            Ok(("{\"a\":1}\n{\"a\":2}\n".to_string(), 2))
+        );
+        let body = b"a,b\n1,x\n".to_vec();
+        assert_eq!(
+            parse_rows(RequestFormat::Csv, body, &csv_options),
+            Ok(("{\"a\":\"1\",\"b\":\"x\"}\n".to_string(), 1))
+        );
+        let body = b"{\"a\":1}\n{\"a\":\n".to_vec();
+        assert_eq!(
+            parse_rows(RequestFormat::Ndjson, body, &csv_options),
+            Err("malformed row (line 2: failed to parse json value: EOF while parsing a value at line 1 column 5)".to_string())
+        );
+
+        let body =
+            br#"{"resourceLogs":[{"scopeLogs":[{"logRecords":[{"body":{"stringValue":"x"}}]}]}]}"#
+                .to_vec();
+        assert_eq!(
+            parse_rows(RequestFormat::Otlp, body, &csv_options),
+            Ok((
+                "{\"attributes\":{},\"body\":\"x\",\"observed_timestamp\":null,\"resource\":{},\"scope_name\":null,\"scope_version\":null,\"severity_number\":null,\"severity_text\":null,\"span_id\":null,\"timestamp\":null,\"trace_id\":null}\n".to_string(),
+                1
+            ))
+        );
+
+        assert_eq!(
+            RequestFormat::from_content_type("application/x-ndjson"),
+            Some(RequestFormat::Ndjson)
+        );
+        assert_eq!(RequestFormat::from_content_type("text/plain"), None);
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Error;
+use crate::load::write_file_atomically;
+
+/// The number of leading bytes of the data file hashed to detect an in-place rewrite.
+const HEAD_SIZE: u64 = 64 * 1024;
+
+/// The state of a resumable load, persisted in a sidecar file next to the data file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadState {
+    #[serde(skip)]
+    path: PathBuf,
+    /// The size, modification time and CRC-32 of the leading bytes of the data file, to detect
+    /// a file changed since the state was saved.
+    file_size: u64,
+    file_mtime: Option<jiff::Timestamp>,
+    file_head_crc: u32,
+    transform: String,
+    /// The number of leading rows of the data file that have been committed.
+    rows_committed: u64,
+}
+
+impl LoadState {
+    /// Returns the path of the state file of the data file.
+    pub fn path_of(file: &Path) -> PathBuf {
+        let mut path = file.as_os_str().to_owned();
+        path.push(".scopeql-state");
+        PathBuf::from(path)
+    }
+
+    /// Opens the state of the previous load of the data file, or a new state if there is none.
+    pub fn open(file: &Path, transform: &str) -> Result<Self, Error> {
+        let path = Self::path_of(file);
+        let make_error = || Error::new(format!("failed to read load state: {}", path.display()));
+
+        let (file_size, file_mtime, file_head_crc) = identify(file)
+            .or_raise(|| Error::new(format!("failed to open data file: {}", file.display())))?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(LoadState {
+                    path,
+                    file_size,
+                    file_mtime,
+                    file_head_crc,
+                    transform: transform.to_string(),
+                    rows_committed: 0,
+                });
+            }
+            Err(err) => return Err(err).or_raise(make_error),
+        };
+
+        let state: LoadState = serde_json::from_str(&content).or_raise(make_error)?;
+        let changed = state.file_size != file_size
+            || state.file_mtime != file_mtime
+            || state.file_head_crc != file_head_crc;
+        if changed || state.transform != transform {
+            bail!(Error::new(format!(
+                "the data file or the transform has changed since the load state was saved; remove {} to load from the beginning",
+                path.display()
+            )));
+        }
+        Ok(LoadState { path, ..state })
+    }
+
+    pub fn rows_committed(&self) -> u64 {
+        self.rows_committed
+    }
+
+    pub fn save(&mut self, rows_committed: u64) -> Result<(), Error> {
+        self.rows_committed = rows_committed;
+        let content = serde_json::to_string(self)
+            .or_raise(|| Error::new("failed to serialize load state".to_string()))?;
+        write_file_atomically(&self.path, content.as_bytes())
+    }
+
+    /// Removes the state file once the load completes.
+    pub fn remove(self) -> Result<(), Error> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).or_raise(|| {
+                Error::new(format!(
+                    "failed to remove load state: {}",
+                    self.path.display()
+                ))
+            }),
+        }
+    }
+}
+
+/// Returns the size, modification time and CRC-32 of the leading bytes of the file.
+fn identify(file: &Path) -> std::io::Result<(u64, Option<jiff::Timestamp>, u32)> {
+    let mut file = File::open(file)?;
+    let metadata = file.metadata()?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|mtime| jiff::Timestamp::try_from(mtime).ok());
+
+    let mut head = Vec::with_capacity(HEAD_SIZE as usize);
+    file.by_ref().take(HEAD_SIZE).read_to_end(&mut head)?;
+    let mut crc = flate2::Crc::new();
+    crc.update(&head);
+    Ok((metadata.len(), mtime, crc.sum()))
+}
+
+/// Tracks the batches that complete out of order, and the number of leading rows covered by
+/// completed batches.
+#[derive(Debug, Default)]
+pub struct CommitTracker {
+    next_batch: u64,
+    completed: BTreeMap<u64, u64>,
+    rows_committed: u64,
+}
+
+impl CommitTracker {
+    /// Marks the batch of the given sequence number and number of rows as completed, and
+    /// returns the number of leading rows committed.
+    pub fn complete(&mut self, batch: u64, rows: u64) -> u64 {
+        self.completed.insert(batch, rows);
+        while let Some(rows) = self.completed.remove(&self.next_batch) {
+            self.rows_committed += rows;
+            self.next_batch += 1;
+        }
+        self.rows_committed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_state() {
+        let dir = std::env::temp_dir().join(format!("scopeql-resume-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("data.csv");
+        std::fs::write(&file, "a\n1\n2\n").unwrap();
+
+        let mut state = LoadState::open(&file, "INSERT INTO t").unwrap();
+        assert_eq!(state.rows_committed(), 0);
+        state.save(1).unwrap();
+        let state = LoadState::open(&file, "INSERT INTO t").unwrap();
+        assert_eq!(state.rows_committed(), 1);
+        assert!(LoadState::open(&file, "INSERT INTO u").is_err());
+
+        // a file rewritten in place with the same size is not resumed
+        std::fs::write(&file, "a\n3\n4\n").unwrap();
+        assert!(LoadState::open(&file, "INSERT INTO t").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_tracker() {
+        let mut tracker = CommitTracker::default();
+        assert_eq!(tracker.complete(1, 20), 0);
+        assert_eq!(tracker.complete(2, 30), 0);
+        assert_eq!(tracker.complete(0, 10), 60);
+        assert_eq!(tracker.complete(4, 50), 60);
+        assert_eq!(tracker.complete(3, 40), 150);
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use exn::Result;
+use exn::ResultExt;
+
+use crate::Error;
+use crate::load::write_file_atomically;
+
+const SPOOL_EXTENSION: &str = "ndjson";
+
+/// A bounded FIFO queue of batches persisted as files in a directory, for batches that cannot
+/// be ingested yet.
+///
+/// Each batch is a file named by its sequence number, so that batches left by a previous run
+/// are replayed in order.
+#[derive(Debug)]
+pub struct Spool {
+    dir: PathBuf,
+    max_size: u64,
+    size: u64,
+    next_seq: u64,
+    /// The sequence numbers and sizes of the spooled batches, oldest first.
+    batches: VecDeque<(u64, u64)>,
+}
+
+impl Spool {
+    /// Opens the spool directory, creating it if it does not exist, and picks up the batches
+    /// spooled by a previous run.
+    pub fn open(dir: PathBuf, max_size: u64) -> Result<Self, Error> {
+        let make_error =
+            || Error::new(format!("failed to open spool directory: {}", dir.display()));
+        std::fs::create_dir_all(&dir).or_raise(make_error)?;
+
+        let mut batches = vec![];
+        for entry in std::fs::read_dir(&dir).or_raise(make_error)? {
+            let entry = entry.or_raise(make_error)?;
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != SPOOL_EXTENSION) {
+                continue;
+            }
+            let Some(seq) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let size = entry.metadata().or_raise(make_error)?.len();
+            batches.push((seq, size));
+        }
+        batches.sort_unstable();
+
+        Ok(Spool {
+            dir,
+            max_size,
+            size: batches.iter().map(|(_, size)| size).sum(),
+            next_seq: batches.last().map_or(0, |(seq, _)| seq + 1),
+            batches: batches.into(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Appends the batch to the spool, and returns false if the spool has no room for it.
+    pub fn push(&mut self, data: &str) -> Result<bool, Error> {
+        let size = data.len() as u64;
+        if self.size + size > self.max_size {
+            return Ok(false);
+        }
+
+        let seq = self.next_seq;
+        write_file_atomically(&self.path(seq), data.as_bytes())?;
+        self.next_seq += 1;
+        self.size += size;
+        self.batches.push_back((seq, size));
+        Ok(true)
+    }
+
+    /// Reads the oldest batch, if any.
+    pub fn front(&self) -> Result<Option<String>, Error> {
+        let Some((seq, _)) = self.batches.front() else {
+            return Ok(None);
+        };
+        let path = self.path(*seq);
+        let data = std::fs::read_to_string(&path)
+            .or_raise(|| Error::new(format!("failed to read spooled batch: {}", path.display())))?;
+        Ok(Some(data))
+    }
+
+    /// Removes the oldest batch once it has been ingested.
+    pub fn pop_front(&mut self) -> Result<(), Error> {
+        let Some((seq, size)) = self.batches.pop_front() else {
+            return Ok(());
+        };
+        self.size -= size;
+        let path = self.path(seq);
+        std::fs::remove_file(&path).or_raise(|| {
+            Error::new(format!(
+                "failed to remove spooled batch: {}",
+                path.display()
+            ))
+        })
+    }
+
+    fn path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{seq:020}.{SPOOL_EXTENSION}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool() {
+        let dir = std::env::temp_dir().join(format!("scopeql-spool-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut spool = Spool::open(dir.clone(), 10).unwrap();
+        assert!(spool.is_empty());
+        assert!(spool.push("{\"a\":1}\n").unwrap());
+        assert!(!spool.push("{\"a\":2}\n").unwrap());
+        spool.pop_front().unwrap();
+        assert!(spool.push("{\"a\":2}\n").unwrap());
+
+        // batches left by a previous run are picked up in order
+        let mut spool = Spool::open(dir.clone(), 20).unwrap();
+        assert!(spool.push("{\"a\":3}\n").unwrap());
+        assert_eq!(spool.len(), 2);
+        assert_eq!(spool.front().unwrap().as_deref(), Some("{\"a\":2}\n"));
+        spool.pop_front().unwrap();
+        assert_eq!(spool.front().unwrap().as_deref(), Some("{\"a\":3}\n"));
+        spool.pop_front().unwrap();
+        assert_eq!(spool.front().unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::load::IngestOptions;
+
+/// Limits the rate of rows and bytes sent by all batches of a load.
+///
+/// Each batch reserves the time it takes to send it at the limited rate, and waits until the
+/// batches before it have used up their reserved time; thus, a batch is sent at once if the load
+/// has been slower than the limits.
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_rows_per_sec: Option<u64>,
+    max_bytes_per_sec: Option<u64>,
+    /// The time when the next batch can be sent.
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(options: &IngestOptions) -> Self {
+        RateLimiter {
+            max_rows_per_sec: options.max_rows_per_sec,
+            max_bytes_per_sec: options.max_bytes_per_sec.map(|size| size as u64),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Waits until a batch of the given numbers of rows and bytes can be sent.
+    pub async fn acquire(&self, rows: u64, bytes: u64) {
+        if self.max_rows_per_sec.is_none() && self.max_bytes_per_sec.is_none() {
+            return;
+        }
+        let start = self.reserve(Instant::now(), rows, bytes);
+        tokio::time::sleep_until(start).await;
+    }
+
+    /// Reserves the time to send the batch, and returns when it can be sent.
+    fn reserve(&self, now: Instant, rows: u64, bytes: u64) -> Instant {
+        let cost = |amount: u64, limit: Option<u64>| match limit {
+            Some(limit) => Duration::from_secs_f64(amount as f64 / limit as f64),
+            None => Duration::ZERO,
+        };
+        let cost = cost(rows, self.max_rows_per_sec).max(cost(bytes, self.max_bytes_per_sec));
+
+        let mut next = self.next.lock().unwrap();
+        let start = next.map_or(now, |next| next.max(now));
+        *next = Some(start + cost);
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter {
+            max_rows_per_sec: Some(100),
+            max_bytes_per_sec: Some(1000),
+            next: Mutex::new(None),
+        };
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now, 100, 10), now);
+        // limited by rows
+        assert_eq!(limiter.reserve(now, 50, 10), now + Duration::from_secs(1));
+        // limited by bytes
+        assert_eq!(
+            limiter.reserve(now, 1, 2000),
+            now + Duration::from_millis(1500)
+        );
+        assert_eq!(
+            limiter.reserve(now + Duration::from_secs(10), 1, 1),
+            now + Duration::from_secs(10)
+        );
+    }
+}
@@ -14,23 +14,30 @@
 
 #![feature(string_from_utf8_lossy_owned)]
 
+use std::io::IsTerminal;
+
 use clap::Parser;
 use logforth::filter::env_filter::EnvFilterBuilder;
 
 use crate::command::Args;
 use crate::command::Command;
-use crate::command::GenerateTarget;
+use crate::command::ConfigCommand;
 use crate::command::Subcommand;
 use crate::config::Config;
 use crate::config::load_config;
+use crate::execute::EXIT_CODE_ERROR;
+use crate::execute::ExecuteOptions;
+use crate::execute::Executor;
 
 mod client;
 mod command;
 mod config;
 mod execute;
+mod generate;
 mod global;
 mod load;
 mod pretty;
+mod progress;
 mod repl;
 mod tokenizer;
 mod version;
@@ -50,55 +57,97 @@ fn main() {
             let config = load_config(config_file);
             repl::entrypoint(&config);
         }
-        Some(Subcommand::Run { files, statements }) => {
-            // command definition ensures exactly one of statement or file is provided
+        Some(Subcommand::Run {
+            files,
+            statements,
+            progress,
+            timing,
+            continue_on_error,
+            stop_on_error: _,
+            echo,
+            dry_run,
+            single_transaction,
+        }) => {
+            // command definition ensures at most one of statement or file is provided
             debug_assert!(
-                files.is_empty() ^ statements.is_empty(),
+                files.is_empty() || statements.is_empty(),
                 "files: {files:?}, statements: {statements:?}"
             );
 
-            let config = load_config(config_file);
-            for stmt in statements {
-                execute::execute(&config, stmt);
+            // a dry run needs neither the server nor a config file
+            let config = match dry_run {
+                true => Config::default(),
+                false => load_config(config_file),
+            };
+            let mut executor = Executor::new(
+                &config,
+                ExecuteOptions {
+                    progress,
+                    timing,
+                    continue_on_error,
+                    echo,
+                    dry_run,
+                    single_transaction,
+                },
+            );
+            let statements_is_empty = statements.is_empty();
+            for (i, stmt) in statements.into_iter().enumerate() {
+                executor.execute(format!("statement #{}", i + 1), stmt);
             }
-            for file in files {
-                match std::fs::read_to_string(&file) {
-                    Ok(content) => execute::execute(&config, content),
-                    Err(err) => {
-                        let file = file.display();
-                        log::error!("failed to read script file {file}: {err}");
-                    }
-                }
+            for file in &files {
+                executor.execute_file(file);
             }
-        }
-        Some(Subcommand::Generate { target, output }) => {
-            let content = match target {
-                GenerateTarget::Config => {
-                    let config = Config::default();
-                    toml::to_string(&config).expect("default config must be always valid")
+            if files.is_empty() && statements_is_empty {
+                if std::io::stdin().is_terminal() {
+                    log::error!(
+                        "no statements provided; pass statements, script files or pipe a script to stdin"
+                    );
+                    std::process::exit(EXIT_CODE_ERROR);
                 }
-            };
+                executor.execute_stdin();
+            }
 
-            if let Some(output) = output {
-                std::fs::write(&output, content).unwrap_or_else(|err| {
-                    let output = output.display();
-                    let target = match target {
-                        GenerateTarget::Config => "configurations",
-                    };
-                    panic!("failed to write {target} to {output}: {err}")
-                });
-            } else {
-                println!("{content}");
+            let exit_code = executor.finish();
+            if exit_code != 0 {
+                std::process::exit(exit_code);
             }
         }
+        Some(Subcommand::Config {
+            command: ConfigCommand::Validate { file },
+        }) => config::validate_config(file.or(config_file)),
+        Some(Subcommand::Generate { target, output }) => generate::generate(target, output),
         Some(Subcommand::Load {
-            file,
+            files,
+            transform,
+            preview,
+            format_options,
+            ingest_options,
+            follow_options,
+        }) => {
+            let config = load_config(config_file);
+            load::load(
+                &config,
+                files,
+                transform,
+                preview,
+                *format_options,
+                ingest_options,
+                follow_options,
+            );
+        }
+        Some(Subcommand::ServeIngest {
             transform,
-            format,
+            relay_options,
+            csv_options,
         }) => {
             let config = load_config(config_file);
-            load::load(&config, file, transform, format);
+            load::serve_ingest(&config, transform, relay_options, csv_options);
         }
+        Some(Subcommand::Infer {
+            files,
+            infer_options,
+            format_options,
+        }) => load::infer(files, *format_options, infer_options),
     }
 }
 
@@ -0,0 +1,270 @@
+// Copyright 2025 ScopeDB, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::IsTerminal;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use indicatif::HumanBytes;
+use indicatif::HumanCount;
+use indicatif::MultiProgress;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use jiff::SignedDuration;
+
+use crate::client::StatementEstimatedProgress;
+use crate::client::StatementProgress;
+
+/// How the progress of a running statement is displayed.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// Display stages, partitions, rows and bytes, and a summary line after completion.
+    Verbose,
+    /// Display a single progress bar.
+    #[default]
+    Compact,
+    /// Do not display progress.
+    Off,
+}
+
+/// A progress view of a single statement execution.
+///
+/// When stderr is not a terminal, progress bars are replaced by plain log lines emitted
+/// periodically.
+#[derive(Debug, Clone)]
+pub struct ProgressView {
+    mode: ProgressMode,
+    bar: ProgressBar,
+    details: Vec<ProgressBar>,
+    last: Arc<Mutex<Option<StatementEstimatedProgress>>>,
+    last_logged: Option<Arc<Mutex<Instant>>>,
+}
+
+impl ProgressView {
+    pub fn new(mode: ProgressMode) -> Self {
+        const BAR_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] {msg:.green.bold.bright} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})";
+        const DETAIL_TEMPLATE: &str = "  {msg}";
+        const NUM_DETAIL_LINES: usize = 3;
+
+        let make_bar = || {
+            ProgressBar::no_length().with_style(ProgressStyle::with_template(BAR_TEMPLATE).unwrap())
+        };
+
+        let plain = mode != ProgressMode::Off && !std::io::stderr().is_terminal();
+        let last_logged = plain.then(|| Arc::new(Mutex::new(Instant::now())));
+
+        let (bar, details) = match mode {
+            _ if plain => (ProgressBar::hidden(), vec![]),
+            ProgressMode::Off => (ProgressBar::hidden(), vec![]),
+            ProgressMode::Compact => (make_bar(), vec![]),
+            ProgressMode::Verbose => {
+                let multi = MultiProgress::new();
+                let bar = multi.add(make_bar());
+                let details = (0..NUM_DETAIL_LINES)
+                    .map(|_| {
+                        multi.add(
+                            ProgressBar::no_length()
+                                .with_style(ProgressStyle::with_template(DETAIL_TEMPLATE).unwrap()),
+                        )
+                    })
+                    .collect();
+                (bar, details)
+            }
+        };
+
+        Self {
+            mode,
+            bar,
+            details,
+            last: Arc::new(Mutex::new(None)),
+            last_logged,
+        }
+    }
+
+    pub fn tick(&self) {
+        self.bar.tick();
+    }
+
+    pub fn update(&self, status: &str, progress: &StatementEstimatedProgress) {
+        self.bar.set_message(status.to_string());
+
+        let details = &progress.details;
+        if details.total_uncompressed_bytes > 0 {
+            self.bar.set_length(details.total_uncompressed_bytes as u64);
+            self.bar.set_position(
+                (details.total_percentage() / 100.0 * details.total_uncompressed_bytes as f64)
+                    as u64,
+            );
+        }
+
+        if let [stages, rows, bytes] = self.details.as_slice() {
+            stages.set_message(format!(
+                "{}  {}",
+                format_stages(details),
+                format_partitions(details)
+            ));
+            rows.set_message(format_rows(details));
+            bytes.set_message(format_bytes(details, progress.nanos_from_started));
+        }
+
+        if let Some(last_logged) = &self.last_logged {
+            const PLAIN_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+            let mut last_logged = last_logged.lock().unwrap();
+            if last_logged.elapsed() >= PLAIN_LOG_INTERVAL {
+                *last_logged = Instant::now();
+                let percentage = details.total_percentage();
+                match self.mode {
+                    ProgressMode::Verbose => log::info!(
+                        "{status} {percentage:.1}%: {}, {}, {}, {}",
+                        format_stages(details),
+                        format_partitions(details),
+                        format_rows(details),
+                        format_bytes(details, progress.nanos_from_started),
+                    ),
+                    ProgressMode::Compact | ProgressMode::Off => {
+                        log::info!("{status} {percentage:.1}%")
+                    }
+                }
+            }
+        }
+
+        *self.last.lock().unwrap() = Some(progress.clone());
+    }
+
+    /// Clears the progress view and returns the summary line if the mode asks for one.
+    pub fn finish(&self) -> Option<String> {
+        self.bar.finish_and_clear();
+        for detail in &self.details {
+            detail.finish_and_clear();
+        }
+
+        if self.mode != ProgressMode::Verbose {
+            return None;
+        }
+
+        let progress = self.last.lock().unwrap().take()?;
+        Some(format_summary(&progress))
+    }
+}
+
+/// Formats the one-line summary of a completed statement.
+pub fn format_summary(progress: &StatementEstimatedProgress) -> String {
+    let details = &progress.details;
+    let run_secs = SignedDuration::from_nanos(progress.nanos_from_started).as_secs_f64();
+    format!(
+        "{}, {}, {}, {} in {run_secs:.3}s",
+        format_stages(details),
+        format_partitions(details),
+        format_rows(details),
+        format_bytes(details, progress.nanos_from_started),
+    )
+}
+
+fn format_stages(details: &StatementProgress) -> String {
+    format!(
+        "stages: {}/{}",
+        details.scanned_stages, details.total_stages
+    )
+}
+
+fn format_partitions(details: &StatementProgress) -> String {
+    let pruned = if details.total_partitions > 0 {
+        details.skipped_partitions as f64 / details.total_partitions as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    format!(
+        "partitions: {}/{} scanned, {} skipped ({pruned:.1}% pruned)",
+        HumanCount(details.scanned_partitions as u64),
+        HumanCount(details.total_partitions as u64),
+        HumanCount(details.skipped_partitions as u64),
+    )
+}
+
+fn format_rows(details: &StatementProgress) -> String {
+    format!(
+        "rows: {}/{} scanned, {} skipped",
+        HumanCount(details.scanned_rows as u64),
+        HumanCount(details.total_rows as u64),
+        HumanCount(details.skipped_rows as u64),
+    )
+}
+
+fn format_bytes(details: &StatementProgress, nanos_from_started: i64) -> String {
+    let run_secs = SignedDuration::from_nanos(nanos_from_started).as_secs_f64();
+    let throughput = if run_secs > 0.0 {
+        (details.scanned_uncompressed_bytes as f64 / run_secs) as u64
+    } else {
+        0
+    };
+
+    format!(
+        "bytes: {} compressed, {}/{} uncompressed ({}/s)",
+        HumanBytes(details.scanned_compressed_bytes as u64),
+        HumanBytes(details.scanned_uncompressed_bytes as u64),
+        HumanBytes(details.total_uncompressed_bytes as u64),
+        HumanBytes(throughput),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_summary() {
+        let progress = StatementEstimatedProgress {
+            nanos_from_submitted: 3_000_000_000,
+            nanos_from_started: 2_000_000_000,
+            details: StatementProgress {
+                total_stages: 3,
+                scanned_stages: 2,
+                total_partitions: 200,
+                scanned_partitions: 50,
+                skipped_partitions: 150,
+                total_rows: 1_500_000,
+                scanned_rows: 1_000_000,
+                skipped_rows: 500_000,
+                scanned_compressed_bytes: 1024,
+                scanned_uncompressed_bytes: 4 * 1024 * 1024,
+                total_uncompressed_bytes: 8 * 1024 * 1024,
+                ..StatementProgress::default()
+            },
+            ..StatementEstimatedProgress::default()
+        };
+        assert_eq!(
+            format_summary(&progress),
+            "stages: 2/3, \
+             partitions: 50/200 scanned, 150 skipped (75.0% pruned), \
+             rows: 1,000,000/1,500,000 scanned, 500,000 skipped, \
+             bytes: 1.00 KiB compressed, 4.00 MiB/8.00 MiB uncompressed (2.00 MiB/s) \
+             in 2.000s"
+        );
+
+        // no division by zero before any partition is planned or scanned
+        let details = StatementProgress::default();
+        assert_eq!(
+            format_partitions(&details),
+            "partitions: 0/0 scanned, 0 skipped (0.0% pruned)"
+        );
+        assert_eq!(
+            format_bytes(&details, 0),
+            "bytes: 0 B compressed, 0 B/0 B uncompressed (0 B/s)"
+        );
+    }
+}
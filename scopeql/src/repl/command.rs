@@ -17,6 +17,7 @@ use clap::Subcommand;
 
 use crate::client::ScopeQLClient;
 use crate::global::rt;
+use crate::progress::ProgressMode;
 
 #[derive(Debug, Parser)]
 #[command(multicall = true)]
@@ -33,6 +34,9 @@ pub enum ReplSubCommand {
     /// Connect to another ScopeDB server.
     #[command(name = "connect")]
     Connect(CommandConnect),
+    /// Set how the progress of running statements is displayed.
+    #[command(name = "progress")]
+    Progress(CommandProgress),
 }
 
 #[derive(Debug, Parser)]
@@ -42,6 +46,13 @@ pub struct CommandConnect {
     pub endpoint: String,
 }
 
+#[derive(Debug, Parser)]
+pub struct CommandProgress {
+    /// The progress display mode.
+    #[arg(value_enum, value_name = "MODE")]
+    pub mode: ProgressMode,
+}
+
 #[derive(Debug, Parser)]
 pub struct CommandCancel {
     /// The ID of the statement to cancel.
@@ -16,8 +16,6 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use clap::Parser;
-use indicatif::ProgressBar;
-use indicatif::ProgressStyle;
 use mea::latch::Latch;
 use nu_ansi_term::Color;
 use nu_ansi_term::Style;
@@ -36,6 +34,8 @@ use scopeql_parser::TokenKind;
 use crate::client::ScopeQLClient;
 use crate::config::Config;
 use crate::global;
+use crate::progress::ProgressMode;
+use crate::progress::ProgressView;
 use crate::repl::command::ReplCommand;
 use crate::repl::command::ReplSubCommand;
 use crate::repl::highlight::ScopeQLHighlighter;
@@ -55,17 +55,17 @@ fn make_file_history() -> Option<FileBackedHistory> {
 }
 
 pub fn entrypoint(config: &Config) {
-    let endpoint = config
+    let connection = config
         .get_default_connection()
         .expect("no default connection in config");
-    let endpoint = endpoint.endpoint().to_owned();
+    let endpoint = connection.endpoint().to_owned();
 
     let mut prompt = CommandLinePrompt::default();
     let mut client = if endpoint.is_empty() {
         None
     } else {
         prompt.set_endpoint(Some(endpoint.clone()));
-        Some(ScopeQLClient::new(endpoint))
+        Some(ScopeQLClient::new(endpoint).with_compression(connection.compression()))
     };
 
     let mut keybindings = default_emacs_keybindings();
@@ -88,6 +88,8 @@ pub fn entrypoint(config: &Config) {
         state = state.with_history(Box::new(history));
     }
 
+    let mut progress_mode = ProgressMode::default();
+
     loop {
         let input = state.read_line(&prompt).expect("failed to read next line");
         let input = match input {
@@ -112,11 +114,18 @@ pub fn entrypoint(config: &Config) {
             match cmd.cmd {
                 ReplSubCommand::Connect(connect) => {
                     let endpoint = connect.endpoint;
-                    client = Some(ScopeQLClient::new(endpoint.clone()));
+                    client = Some(
+                        ScopeQLClient::new(endpoint.clone())
+                            .with_compression(connection.compression()),
+                    );
                     println!("connected to {endpoint}");
                     prompt.set_endpoint(Some(endpoint));
                 }
                 ReplSubCommand::Cancel(cancel) => cancel.run(client.as_ref()),
+                ReplSubCommand::Progress(progress) => {
+                    progress_mode = progress.mode;
+                    println!("progress display mode updated");
+                }
             }
             continue;
         }
@@ -173,35 +182,25 @@ pub fn entrypoint(config: &Config) {
             let statement_id = uuid::Uuid::now_v7();
             println!("StatementID: {statement_id}");
 
-            let pb_style = "{spinner:.green} [{elapsed_precise}] {msg:.green.bold.bright} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})";
-            let pb = ProgressBar::no_length()
-                .with_style(ProgressStyle::with_template(pb_style).unwrap());
+            let view = ProgressView::new(progress_mode);
             let stop_pb = Arc::new(Latch::new(1));
 
             global::rt().spawn({
-                let pb = pb.clone();
+                let view = view.clone();
                 let stop_pb = stop_pb.clone();
                 async move {
                     while stop_pb.try_wait().is_err() {
                         tokio::time::sleep(Duration::from_millis(42)).await;
-                        pb.tick();
+                        view.tick();
                     }
                 }
             });
 
             let output = global::rt().block_on({
-                let pb = pb.clone();
+                let view = view.clone();
                 async move {
                     let fut = client.execute_statement(statement_id, stmt, |status, progress| {
-                        pb.set_message(status.to_string());
-                        if progress.details.total_uncompressed_bytes > 0 {
-                            pb.set_length(progress.details.total_uncompressed_bytes as u64);
-                            pb.set_position(
-                                (progress.details.total_percentage() / 100.0
-                                    * progress.details.total_uncompressed_bytes as f64)
-                                    as u64,
-                            );
-                        }
+                        view.update(status, &progress);
                     });
 
                     tokio::select! {
@@ -212,10 +211,15 @@ pub fn entrypoint(config: &Config) {
             });
 
             stop_pb.count_down();
-            pb.finish_and_clear();
+            let summary = view.finish();
 
             match output {
-                Some(Ok(output)) => println!("{output}"),
+                Some(Ok(output)) => {
+                    println!("{output}");
+                    if let Some(summary) = summary {
+                        println!("{summary}");
+                    }
+                }
                 Some(Err(err)) => println!("{err:?}"),
                 None => {
                     let output = global::rt().block_on(client.cancel_statement(statement_id));
@@ -47,3 +47,53 @@ pub fn run_tokenizer(source: &'_ str) -> Result<Vec<Token<'_>>, Error> {
     }
     Ok(tokens)
 }
+
+/// Computes the fingerprint of a statement.
+///
+/// The fingerprint is insensitive to whitespaces, comments and the case of keywords and
+/// unquoted identifiers, so that the same statement formatted differently has the same
+/// fingerprint.
+pub fn fingerprint(tokens: &[Token<'_>]) -> u64 {
+    // 64-bit FNV-1a, which is stable across platforms and releases
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    for token in tokens {
+        let slice = &token.source[token.span.clone()];
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::EOI => continue,
+            TokenKind::Ident => write(slice.to_lowercase().as_bytes()),
+            kind if kind.is_keyword() => write(slice.to_uppercase().as_bytes()),
+            _ => write(slice.as_bytes()),
+        }
+        // separate tokens so that `ab` and `a b` differ
+        write(b" ");
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint_of = |source| fingerprint(&run_tokenizer(source).unwrap());
+
+        assert_eq!(
+            fingerprint_of("select 1"),
+            fingerprint_of("SELECT  /* one */ 1 -- comment")
+        );
+        assert_eq!(fingerprint_of("from T"), fingerprint_of("FROM t"));
+        assert_ne!(fingerprint_of("select 'a'"), fingerprint_of("select 'A'"));
+        assert_ne!(fingerprint_of("select ab"), fingerprint_of("select a b"));
+    }
+}